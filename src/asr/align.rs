@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use crate::{
+    asr::subtitle::{query_result, SubtitleResult, SubtitleSource},
+    client::{Client, RawResponse},
+    error::*,
+    openapi::Multipart,
+    poll::{watch, JobEvent, JobStatus, PollPolicy},
+};
+use futures::Stream;
+use http::{header, HeaderValue, Method};
+use reqwest::Body;
+use serde_json::json;
+use smart_default::SmartDefault;
+use tracing::*;
+
+pub const AUDIO_TEXT_FIELD: &str = "audio-text";
+
+#[derive(Debug, Clone, SmartDefault)]
+pub struct AlignRequestBuilder {
+    pub params: HashMap<String, String>,
+    pub source: Option<SubtitleSource>,
+    pub text: Option<String>,
+}
+
+macro_rules! impl_with_params {
+    ($fun: ident) => {
+        impl AlignRequestBuilder {
+            pub fn $fun(mut self, value: impl Into<String>) -> Self {
+                self.params.insert(stringify!($fun).into(), value.into());
+                self
+            }
+        }
+    };
+    ($fun: ident, $($more_fun: ident), +) => {
+        impl_with_params!($fun);
+        impl_with_params!($($more_fun),+);
+    }
+}
+
+impl_with_params!(appid, caption_type, sta_punc_mode);
+
+impl AlignRequestBuilder {
+    pub fn source(mut self, source: impl Into<SubtitleSource>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    pub fn build(self) -> Result<AlignRequest> {
+        let Self {
+            params,
+            source,
+            text,
+        } = self;
+        let source = source.ok_or(Error::AlignRequestBuild("source"))?;
+        let text = text
+            .filter(|text| !text.trim().is_empty())
+            .ok_or(Error::AlignRequestBuild("text"))?;
+        if !params.contains_key("appid") {
+            return Err(Error::AlignRequestBuild("appid"));
+        }
+        Ok(AlignRequest {
+            params,
+            source,
+            text,
+        })
+    }
+}
+
+pub struct AlignRequest {
+    pub params: HashMap<String, String>,
+    pub source: SubtitleSource,
+    pub text: String,
+}
+
+impl AlignRequest {
+    pub fn builder() -> AlignRequestBuilder {
+        AlignRequestBuilder::default()
+    }
+
+    pub async fn call(&self, client: &Client) -> Result<AlignResponse> {
+        let Self {
+            params,
+            source,
+            text,
+        } = self;
+        let queries = params
+            .iter()
+            .map(|a| (a.0.to_owned(), a.1.to_owned()))
+            .collect::<Vec<_>>();
+        let (content_type, body) = match source {
+            SubtitleSource::Binary { typ, data } => Multipart::new()
+                .file(
+                    "data",
+                    &format!("audio.{}", typ),
+                    &format!("audio/{}", typ),
+                    data,
+                )
+                .text(AUDIO_TEXT_FIELD, text)
+                .finish()?,
+            SubtitleSource::Url(url) => (
+                HeaderValue::from_static("application/json"),
+                Body::from(serde_json::to_string(
+                    &json!({"url": url, AUDIO_TEXT_FIELD: text}),
+                )?),
+            ),
+        };
+        let rep = client
+            .call(
+                Method::POST,
+                "/api/v1/vc/ata/submit",
+                queries,
+                vec![(header::CONTENT_TYPE, content_type)],
+                Some(body),
+            )
+            .await?;
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
+        let rep: AlignResponse = raw.json()?;
+        if rep.code != 0 {
+            return Err(raw.api_error());
+        }
+        Ok(rep)
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct AlignResponse {
+    pub code: i64,
+    pub message: String,
+    pub id: String,
+}
+
+impl AlignResponse {
+    pub async fn query(
+        &self,
+        appid: impl AsRef<str>,
+        client: &Client,
+    ) -> Result<JobStatus<SubtitleResult>> {
+        query_result(
+            client,
+            "/api/v1/vc/ata/query",
+            "align",
+            appid.as_ref(),
+            &self.id,
+        )
+        .await
+    }
+
+    pub fn events(
+        &self,
+        appid: impl Into<String>,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> impl Stream<Item = JobEvent<SubtitleResult>> {
+        let (rep, appid, client) = (self.clone(), appid.into(), client.clone());
+        watch(policy.into().poller(&self.id), move || {
+            let (rep, appid, client) = (rep.clone(), appid.clone(), client.clone());
+            async move { rep.query(appid, &client).await }
+        })
+    }
+
+    pub async fn waiting_result(
+        &self,
+        appid: impl AsRef<str>,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> Result<SubtitleResult> {
+        trace!("waiting appid={}, id={}", appid.as_ref(), self.id);
+        let mut poller = policy.into().poller(&self.id);
+        loop {
+            poller.next_attempt().await?;
+            match poller.attempt(self.query(appid.as_ref(), client)).await {
+                Ok(JobStatus::Done(rep)) => return Ok(rep),
+                Ok(_) => trace!(
+                    "align job {} pending, attempt={}",
+                    self.id,
+                    poller.attempts()
+                ),
+                Err(e) if e.is_retryable() => warn!(
+                    "align job {} query failed, retrying: {}, attempt={}",
+                    self.id,
+                    e,
+                    poller.attempts()
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_align_submit_query() -> Result<()> {
+    use crate::caption::Captions;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let queries = Arc::new(AtomicUsize::new(0));
+    let counter = queries.clone();
+    let client = Client::builder()
+        .transport(crate::transport::json_mock(move |req| {
+            let query = req.url().query().unwrap_or_default().to_string();
+            match req.url().path() {
+                "/api/v1/vc/ata/submit" => {
+                    assert!(query.contains("appid=100"));
+                    assert!(query.contains("caption_type=speech"));
+                    let body = String::from_utf8_lossy(req.body().unwrap().as_bytes().unwrap())
+                        .to_string();
+                    assert!(req.headers()[header::CONTENT_TYPE]
+                        .to_str()
+                        .unwrap()
+                        .starts_with("multipart/form-data; boundary="));
+                    assert!(body.contains(
+                        "name=\"data\"; filename=\"audio.wav\"\r\nContent-Type: audio/wav\r\n\r\nRIFF\r\n"
+                    ));
+                    assert!(body.contains("name=\"audio-text\"\r\n\r\n你好\n世界\r\n"));
+                    json!({"code": 0, "message": "Success", "id": "ata-job"})
+                }
+                "/api/v1/vc/ata/query" => {
+                    assert!(query.contains("id=ata-job"));
+                    match counter.fetch_add(1, Ordering::SeqCst) {
+                        0 => json!({"code": 2000, "message": "running", "id": "ata-job"}),
+                        _ => json!({
+                            "code": 0,
+                            "message": "Success",
+                            "id": "ata-job",
+                            "duration": 2.0,
+                            "attribute": {},
+                            "utterances": [
+                                {"start_time": 0, "end_time": 800, "text": "你好", "attribute": {},
+                                 "words": [{"start_time": 0, "end_time": 400, "text": "你", "attribute": {}},
+                                           {"start_time": 400, "end_time": 800, "text": "好", "attribute": {}}]},
+                                {"start_time": 1000, "end_time": 2000, "text": "世界", "attribute": {}, "words": []}
+                            ]
+                        }),
+                    }
+                }
+                path => unreachable!("{}", path),
+            }
+        }))
+        .build()?;
+
+    assert!(matches!(
+        AlignRequest::builder()
+            .appid("100")
+            .source(SubtitleSource::Url("https://example.com/a.wav".into()))
+            .build(),
+        Err(Error::AlignRequestBuild("text"))
+    ));
+
+    let result = AlignRequest::builder()
+        .appid("100")
+        .caption_type("speech")
+        .source(SubtitleSource::Binary {
+            typ: "wav".into(),
+            data: b"RIFF".to_vec(),
+        })
+        .text("你好\n世界")
+        .build()?
+        .call(&client)
+        .await?
+        .waiting_result("100", &client, PollPolicy::fixed(std::time::Duration::ZERO))
+        .await?;
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+    assert_eq!(result.utterances.len(), 2);
+    assert_eq!(result.utterances[0].words[1].start_time, 400);
+    assert_eq!(
+        result.to_srt(),
+        "1\n00:00:00,000 --> 00:00:00,800\n你好\n\n2\n00:00:01,000 --> 00:00:02,000\n世界\n\n"
+    );
+    Ok(())
+}
@@ -0,0 +1,458 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    asr::{
+        record::{RecordAsrRequest, RecordAsrResponse, RecordAsrResult},
+        subtitle::{SubtitleRequest, SubtitleResponse, SubtitleResult},
+    },
+    client::Client,
+    error::*,
+    poll::{JobStatus, PollPolicy, Poller},
+    store::{is_job_failure, JobStore, StoredJob},
+};
+use futures::{channel::mpsc, stream::FuturesUnordered, Stream, StreamExt};
+use smart_default::SmartDefault;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+pub enum BatchRequest {
+    Record(Box<RecordAsrRequest>),
+    Subtitle(SubtitleRequest),
+}
+
+impl From<RecordAsrRequest> for BatchRequest {
+    fn from(value: RecordAsrRequest) -> Self {
+        Self::Record(Box::new(value))
+    }
+}
+
+impl From<SubtitleRequest> for BatchRequest {
+    fn from(value: SubtitleRequest) -> Self {
+        Self::Subtitle(value)
+    }
+}
+
+impl BatchRequest {
+    pub fn appid(&self) -> &str {
+        match self {
+            Self::Record(req) => &req.app.appid,
+            Self::Subtitle(req) => req
+                .params
+                .get("appid")
+                .map(String::as_str)
+                .unwrap_or_default(),
+        }
+    }
+
+    async fn submit(self, client: &Client) -> Result<BatchJob> {
+        match self {
+            Self::Record(req) => Ok(BatchJob::Record(req.call(client).await?)),
+            Self::Subtitle(req) => {
+                let appid = req.params.get("appid").cloned().unwrap_or_default();
+                Ok(BatchJob::Subtitle(req.call(client).await?, appid))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum BatchResult {
+    Record(RecordAsrResult),
+    Subtitle(SubtitleResult),
+}
+
+#[derive(Debug)]
+pub struct BatchItem {
+    pub index: usize,
+    pub id: Option<String>,
+    pub result: Result<BatchResult>,
+}
+
+enum BatchJob {
+    Record(RecordAsrResponse),
+    Subtitle(SubtitleResponse, String),
+}
+
+impl BatchJob {
+    fn id(&self) -> &str {
+        match self {
+            Self::Record(rep) => &rep.id,
+            Self::Subtitle(rep, _) => &rep.id,
+        }
+    }
+
+    fn stored(&self) -> StoredJob {
+        match self {
+            Self::Record(rep) => StoredJob::record(rep),
+            Self::Subtitle(rep, appid) => StoredJob::subtitle(rep, appid),
+        }
+    }
+
+    async fn query(&self, client: &Client) -> Result<JobStatus<BatchResult>> {
+        Ok(match self {
+            Self::Record(rep) => match rep.query(client).await? {
+                JobStatus::Done(result) => JobStatus::Done(BatchResult::Record(result)),
+                JobStatus::Queued => JobStatus::Queued,
+                JobStatus::Processing => JobStatus::Processing,
+            },
+            Self::Subtitle(rep, appid) => match rep.query(appid, client).await? {
+                JobStatus::Done(result) => JobStatus::Done(BatchResult::Subtitle(result)),
+                JobStatus::Queued => JobStatus::Queued,
+                JobStatus::Processing => JobStatus::Processing,
+            },
+        })
+    }
+}
+
+#[derive(Clone, SmartDefault)]
+pub struct BatchTranscriber {
+    #[default(8)]
+    pub concurrency: usize,
+    pub rate_limit: Option<Duration>,
+    #[default(PollPolicy::new().interval(Duration::from_secs(5)))]
+    pub policy: PollPolicy,
+    pub store: Option<Arc<dyn JobStore>>,
+}
+
+impl BatchTranscriber {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.rate_limit = Some(interval);
+        self
+    }
+
+    pub fn submits_per_second(self, submits: u32) -> Self {
+        self.rate_limit(Duration::from_secs(1) / submits.max(1))
+    }
+
+    pub fn policy(mut self, policy: PollPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn store(mut self, store: impl JobStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    pub fn run<R>(
+        &self,
+        client: &Client,
+        requests: impl IntoIterator<Item = R>,
+    ) -> impl Stream<Item = BatchItem>
+    where
+        R: Into<BatchRequest>,
+    {
+        let (tx, rx) = mpsc::unbounded();
+        let requests = requests.into_iter().map(Into::into).collect::<Vec<_>>();
+        tokio::spawn(drive(self.clone(), client.clone(), requests, tx));
+        rx
+    }
+}
+
+struct PendingJob {
+    index: usize,
+    job: BatchJob,
+    poller: Poller,
+    due: Instant,
+}
+
+async fn drive(
+    batch: BatchTranscriber,
+    client: Client,
+    requests: Vec<BatchRequest>,
+    tx: mpsc::UnboundedSender<BatchItem>,
+) {
+    let total = requests.len();
+    let mut queue = requests.into_iter().enumerate().collect::<VecDeque<_>>();
+    let mut submitting = FuturesUnordered::new();
+    let mut querying = FuturesUnordered::new();
+    let mut pending: Vec<PendingJob> = Vec::new();
+    let mut next_submit: HashMap<String, Instant> = HashMap::new();
+    let mut done = 0;
+
+    info!("batch of {} jobs, concurrency={}", total, batch.concurrency);
+
+    loop {
+        let now = Instant::now();
+        let mut items = vec![];
+
+        while submitting.len() < batch.concurrency {
+            let ready = queue.iter().position(|(_, req)| {
+                next_submit
+                    .get(req.appid())
+                    .into_iter()
+                    .all(|at| *at <= now)
+            });
+            let Some((index, req)) = ready.and_then(|i| queue.remove(i)) else {
+                break;
+            };
+            if let Some(interval) = batch.rate_limit {
+                next_submit.insert(req.appid().to_string(), now + interval);
+            }
+            let client = client.clone();
+            submitting.push(async move { (index, req.submit(&client).await) });
+        }
+
+        let mut i = 0;
+        while i < pending.len() {
+            if pending[i].due > now {
+                i += 1;
+                continue;
+            }
+            let mut job = pending.swap_remove(i);
+            match job.poller.try_attempt() {
+                Ok(_) => {
+                    let client = client.clone();
+                    querying.push(async move {
+                        let result = job.poller.attempt(job.job.query(&client)).await;
+                        (job, result)
+                    });
+                }
+                Err(e) => items.push(finish(&batch, job, Err(e))),
+            }
+        }
+
+        if items.is_empty()
+            && queue.is_empty()
+            && submitting.is_empty()
+            && querying.is_empty()
+            && pending.is_empty()
+        {
+            break;
+        }
+
+        let wake = pending
+            .iter()
+            .map(|job| job.due)
+            .chain(
+                queue
+                    .iter()
+                    .filter(|_| submitting.len() < batch.concurrency)
+                    .filter_map(|(_, req)| next_submit.get(req.appid()).copied()),
+            )
+            .min();
+
+        if items.is_empty() {
+            tokio::select! {
+                Some((index, result)) = submitting.next(), if !submitting.is_empty() => {
+                    match result {
+                        Ok(job) => {
+                            debug!("batch job #{} submitted as {}", index, job.id());
+                            save(&batch, || job.stored());
+                            pending.push(PendingJob {
+                                index,
+                                poller: batch.policy.poller(job.id()),
+                                job,
+                                due: Instant::now(),
+                            });
+                        }
+                        Err(e) => items.push(BatchItem {
+                            index,
+                            id: None,
+                            result: Err(e),
+                        }),
+                    }
+                }
+                Some((mut job, result)) = querying.next(), if !querying.is_empty() => {
+                    let outcome = match result {
+                        Ok(JobStatus::Done(result)) => Some(Ok(result)),
+                        Ok(_) => None,
+                        Err(e) if e.is_retryable() => {
+                            warn!("batch job {} query failed: {}, retrying", job.job.id(), e);
+                            None
+                        }
+                        Err(e) => Some(Err(e)),
+                    };
+                    match outcome {
+                        Some(result) => items.push(finish(&batch, job, result)),
+                        None => match job.poller.next_delay() {
+                            Ok(delay) => {
+                                job.due = Instant::now() + delay;
+                                pending.push(job);
+                            }
+                            Err(e) => items.push(finish(&batch, job, Err(e))),
+                        },
+                    }
+                }
+                _ = tokio::time::sleep_until(wake.unwrap_or(now)), if wake.is_some() => {}
+            }
+        }
+
+        for item in items {
+            done += 1;
+            if tx.unbounded_send(item).is_err() {
+                return;
+            }
+            debug!(
+                "batch progress: {}/{} done, {} submitting, {} pending",
+                done,
+                total,
+                submitting.len(),
+                pending.len() + querying.len()
+            );
+        }
+    }
+
+    info!("batch finished: {}/{} jobs", done, total);
+}
+
+fn finish(batch: &BatchTranscriber, job: PendingJob, result: Result<BatchResult>) -> BatchItem {
+    save(batch, || {
+        let mut stored = job.job.stored();
+        match &result {
+            Ok(BatchResult::Record(r)) => stored.succeed(to_value(r)),
+            Ok(BatchResult::Subtitle(r)) => stored.succeed(to_value(r)),
+            Err(e) if is_job_failure(e) => stored.fail(e),
+            Err(_) => {}
+        }
+        stored
+    });
+    BatchItem {
+        index: job.index,
+        id: Some(job.job.id().to_string()),
+        result,
+    }
+}
+
+fn to_value(result: &impl serde::Serialize) -> serde_json::Value {
+    serde_json::to_value(result).unwrap_or_default()
+}
+
+fn save(batch: &BatchTranscriber, job: impl FnOnce() -> StoredJob) {
+    if let Some(store) = &batch.store {
+        let job = job();
+        if let Err(e) = store.save(&job) {
+            warn!("failed to store batch job {}: {}", job.id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(start_paused = true)]
+async fn test_batch_transcriber() -> Result<()> {
+    use crate::transport::MockResponse;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    let submits = Arc::new(Mutex::new(Vec::<Instant>::new()));
+    let outstanding = Arc::new(AtomicUsize::new(0));
+    let max_outstanding = Arc::new(AtomicUsize::new(0));
+    let polls = Mutex::new(HashMap::<String, usize>::new());
+
+    let client = Client::builder()
+        .transport(crate::transport::json_mock({
+            let (submits, outstanding, max_outstanding) =
+                (submits.clone(), outstanding.clone(), max_outstanding.clone());
+            move |req| {
+                let body = crate::transport::request_json(req);
+                if req.url().path() == "/api/v1/auc/submit" {
+                    submits.lock().unwrap().push(Instant::now());
+                    let url = body["audio"]["url"].as_str().unwrap().to_string();
+                    let rep = match url.ends_with("bad.mp3") {
+                        true => serde_json::json!({"resp": {"id": "", "code": 1001, "message": "invalid"}}),
+                        false => {
+                            let n = outstanding.fetch_add(1, Ordering::SeqCst) + 1;
+                            max_outstanding.fetch_max(n, Ordering::SeqCst);
+                            serde_json::json!({"resp": {"id": url, "code": 1000, "message": "ok"}})
+                        }
+                    };
+                    return MockResponse::json(rep).delay(Duration::from_millis(5));
+                }
+                let id = body["id"].as_str().unwrap().to_string();
+                let mut polls = polls.lock().unwrap();
+                let n = polls.entry(id.clone()).or_default();
+                *n += 1;
+                let wait = id.trim_end_matches(".mp3").rsplit('/').next().unwrap().parse::<usize>().unwrap();
+                let rep = match *n > wait {
+                    true if id.ends_with("example.com/0.mp3") && *n == 1 => {
+                        serde_json::json!({"resp": {"id": id, "code": 1005, "message": "busy"}})
+                    }
+                    true => {
+                        outstanding.fetch_sub(1, Ordering::SeqCst);
+                        serde_json::json!({"resp": {"id": id, "code": 1000, "message": "ok",
+                            "additions": {}, "utterances": []}})
+                    }
+                    false => serde_json::json!({"resp": {"id": id, "code": 2000, "message": "processing"}}),
+                };
+                MockResponse::json(rep)
+            }
+        }))
+        .build()?;
+
+    let request = |appid: &str, url: String| {
+        RecordAsrRequest::builder()
+            .appid(appid)
+            .token("token")
+            .cluster("cluster")
+            .uid("uid")
+            .url(url)
+            .build()
+    };
+    let requests = [3, 1, 0, 2]
+        .iter()
+        .map(|n| format!("https://example.com/{}.mp3", n))
+        .chain(["https://example.com/bad.mp3".to_string()])
+        .map(|url| request("100", url))
+        .collect::<Result<Vec<_>>>()?;
+
+    let items = BatchTranscriber::new()
+        .concurrency(2)
+        .rate_limit(Duration::from_millis(1))
+        .policy(PollPolicy::fixed(Duration::from_millis(10)))
+        .run(&client, requests)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(items.len(), 5);
+    // Each submit takes 5ms, so with concurrency 2 every third submit has to
+    // wait for the first of the previous two to finish.
+    let starts = std::mem::take(&mut *submits.lock().unwrap());
+    assert_eq!(starts.len(), 5);
+    assert!(starts
+        .windows(3)
+        .all(|w| w[2] - w[0] >= Duration::from_millis(5)));
+    assert!(starts
+        .windows(2)
+        .any(|w| w[1] - w[0] < Duration::from_millis(5)));
+    assert_eq!(max_outstanding.load(Ordering::SeqCst), 4);
+    let failed = items.iter().find(|i| i.result.is_err()).unwrap();
+    assert_eq!(failed.index, 4);
+    assert_eq!(failed.id, None);
+    let order = items
+        .iter()
+        .filter(|i| i.result.is_ok())
+        .map(|i| i.index)
+        .collect::<Vec<_>>();
+    assert_eq!(order, vec![1, 2, 3, 0]);
+
+    let requests = vec![
+        request("100", "https://example.com/a/0.mp3".into())?,
+        request("100", "https://example.com/b/0.mp3".into())?,
+        request("200", "https://example.com/c/0.mp3".into())?,
+    ];
+    let started = Instant::now();
+    let arrivals = BatchTranscriber::new()
+        .concurrency(1)
+        .rate_limit(Duration::from_secs(60))
+        .policy(PollPolicy::fixed(Duration::from_millis(10)))
+        .run(&client, requests)
+        .map(|item| (item.index, item.result.is_ok(), started.elapsed()))
+        .collect::<Vec<_>>()
+        .await;
+    assert!(arrivals.iter().all(|a| a.1));
+    assert_eq!(arrivals[2].0, 1);
+    assert!(arrivals[2].2 >= Duration::from_secs(60));
+    assert!(arrivals[..2].iter().all(|a| a.2 < Duration::from_secs(1)));
+    Ok(())
+}
@@ -0,0 +1,598 @@
+use std::sync::Arc;
+
+use crate::{
+    asr::record::{User, Utterance},
+    auth::Anonymous,
+    client::{Client, RawResponse},
+    error::*,
+    poll::{watch, JobEvent, JobStatus, PollPolicy},
+};
+use futures::Stream;
+use http::{header, HeaderName, HeaderValue, Method};
+use rand::Rng;
+use reqwest::Body;
+use serde_with::skip_serializing_none;
+use smart_default::SmartDefault;
+use tracing::{error, trace, warn};
+
+pub const RESOURCE_ID: &str = "volc.bigasr.auc";
+
+pub const X_API_APP_KEY: HeaderName = HeaderName::from_static("x-api-app-key");
+pub const X_API_ACCESS_KEY: HeaderName = HeaderName::from_static("x-api-access-key");
+pub const X_API_RESOURCE_ID: HeaderName = HeaderName::from_static("x-api-resource-id");
+pub const X_API_REQUEST_ID: HeaderName = HeaderName::from_static("x-api-request-id");
+pub const X_API_SEQUENCE: HeaderName = HeaderName::from_static("x-api-sequence");
+pub const X_API_STATUS_CODE: HeaderName = HeaderName::from_static("x-api-status-code");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BigModelStatus {
+    Success,
+    Processing,
+    Queued,
+    SilentAudio,
+    InvalidParams,
+    EmptyAudio,
+    InvalidAudioFormat,
+    ServerBusy,
+    InternalError,
+    Other(i64),
+}
+
+impl BigModelStatus {
+    pub fn code(&self) -> i64 {
+        match self {
+            BigModelStatus::Success => 20000000,
+            BigModelStatus::Processing => 20000001,
+            BigModelStatus::Queued => 20000002,
+            BigModelStatus::SilentAudio => 20000003,
+            BigModelStatus::InvalidParams => 45000001,
+            BigModelStatus::EmptyAudio => 45000002,
+            BigModelStatus::InvalidAudioFormat => 45000151,
+            BigModelStatus::ServerBusy => 55000031,
+            BigModelStatus::InternalError => 55000000,
+            BigModelStatus::Other(code) => *code,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, BigModelStatus::Success)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, BigModelStatus::Processing | BigModelStatus::Queued)
+    }
+
+    pub fn is_failure(&self) -> bool {
+        !self.is_success() && !self.is_pending()
+    }
+
+    pub(crate) fn from_response(raw: &RawResponse) -> Option<Self> {
+        raw.headers
+            .get(X_API_STATUS_CODE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<i64>().ok())
+            .map(Self::from)
+    }
+}
+
+impl From<i64> for BigModelStatus {
+    fn from(value: i64) -> Self {
+        match value {
+            20000000 => BigModelStatus::Success,
+            20000001 => BigModelStatus::Processing,
+            20000002 => BigModelStatus::Queued,
+            20000003 => BigModelStatus::SilentAudio,
+            45000001 => BigModelStatus::InvalidParams,
+            45000002 => BigModelStatus::EmptyAudio,
+            45000151 => BigModelStatus::InvalidAudioFormat,
+            55000031 => BigModelStatus::ServerBusy,
+            55000000 => BigModelStatus::InternalError,
+            code => BigModelStatus::Other(code),
+        }
+    }
+}
+
+impl From<BigModelStatus> for i64 {
+    fn from(value: BigModelStatus) -> Self {
+        value.code()
+    }
+}
+
+pub(crate) fn new_request_id() -> String {
+    let mut bytes = rand::thread_rng().gen::<[u8; 16]>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+pub(crate) fn api_client(client: &Client) -> Client {
+    Client {
+        auth: Arc::new(Anonymous),
+        ..client.clone()
+    }
+}
+
+pub(crate) fn api_headers(
+    appid: &str,
+    token: &str,
+    resource_id: &str,
+    request_id: &str,
+) -> Result<Vec<(HeaderName, HeaderValue)>> {
+    Ok(vec![
+        (X_API_APP_KEY, HeaderValue::from_str(appid)?),
+        (X_API_ACCESS_KEY, HeaderValue::from_str(token)?),
+        (X_API_RESOURCE_ID, HeaderValue::from_str(resource_id)?),
+        (X_API_REQUEST_ID, HeaderValue::from_str(request_id)?),
+        (X_API_SEQUENCE, HeaderValue::from_static("-1")),
+        (
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        ),
+    ])
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct BigModelAudio {
+    pub url: Option<String>,
+    pub data: Option<String>,
+    pub format: Option<String>,
+    pub codec: Option<String>,
+    pub rate: Option<i32>,
+    pub bits: Option<i32>,
+    pub channel: Option<i32>,
+    pub language: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct Corpus {
+    pub boosting_table_name: Option<String>,
+    pub correct_table_name: Option<String>,
+    pub context: Option<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq, SmartDefault)]
+pub struct BigModelOptions {
+    #[default("bigmodel".to_string())]
+    pub model_name: String,
+    pub enable_itn: Option<bool>,
+    pub enable_punc: Option<bool>,
+    pub enable_ddc: Option<bool>,
+    pub enable_speaker_info: Option<bool>,
+    pub enable_channel_split: Option<bool>,
+    pub show_utterances: Option<bool>,
+    pub vad_segment: Option<bool>,
+    pub sensitive_words_filter: Option<String>,
+    pub corpus: Option<Corpus>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct BigModelRequest {
+    #[serde(skip)]
+    pub appid: String,
+    #[serde(skip)]
+    pub token: String,
+    #[serde(skip)]
+    pub resource_id: String,
+    pub user: User,
+    pub audio: BigModelAudio,
+    pub request: BigModelOptions,
+    pub callback: Option<String>,
+    pub callback_data: Option<String>,
+}
+
+impl BigModelRequest {
+    pub fn builder() -> BigModelRequestBuilder {
+        BigModelRequestBuilder::default()
+    }
+
+    pub async fn call(self, client: &Client) -> Result<BigModelResponse> {
+        let id = new_request_id();
+        let rep = api_client(client)
+            .call(
+                Method::POST,
+                "/api/v3/auc/bigmodel/submit",
+                vec![],
+                api_headers(&self.appid, &self.token, &self.resource_id, &id)?,
+                Some(Body::from(serde_json::to_string(&self)?)),
+            )
+            .await?;
+
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
+
+        match BigModelStatus::from_response(&raw) {
+            Some(BigModelStatus::Success) => Ok(BigModelResponse {
+                id,
+                appid: self.appid,
+                token: self.token,
+                resource_id: self.resource_id,
+                log_id: raw
+                    .headers
+                    .get("x-tt-logid")
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_string()),
+            }),
+            _ => Err(raw.api_error()),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct BigModelResponse {
+    pub id: String,
+    pub appid: String,
+    pub token: String,
+    pub resource_id: String,
+    pub log_id: Option<String>,
+}
+
+impl BigModelResponse {
+    pub async fn query(&self, client: &Client) -> Result<JobStatus<BigModelResult>> {
+        let rep = api_client(client)
+            .call_idempotent(
+                Method::POST,
+                "/api/v3/auc/bigmodel/query",
+                vec![],
+                api_headers(&self.appid, &self.token, &self.resource_id, &self.id)?,
+                Some(Body::from("{}")),
+            )
+            .await?;
+
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
+
+        match BigModelStatus::from_response(&raw) {
+            Some(BigModelStatus::Success) => Ok(JobStatus::Done(raw.json()?)),
+            Some(BigModelStatus::Queued) => Ok(JobStatus::Queued),
+            Some(BigModelStatus::Processing) => Ok(JobStatus::Processing),
+            _ => {
+                let e = raw.api_error();
+                if !e.is_retryable() {
+                    error!("bigmodel asr job {} failed: {}", self.id, e);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    pub fn events(
+        &self,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> impl Stream<Item = JobEvent<BigModelResult>> {
+        let (rep, client) = (self.clone(), client.clone());
+        watch(policy.into().poller(&self.id), move || {
+            let (rep, client) = (rep.clone(), client.clone());
+            async move { rep.query(&client).await }
+        })
+    }
+
+    pub async fn waiting_result(
+        self,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> Result<BigModelResult> {
+        let mut poller = policy.into().poller(&self.id);
+
+        loop {
+            poller.next_attempt().await?;
+
+            match poller.attempt(self.query(client)).await {
+                Ok(JobStatus::Done(rep)) => return Ok(rep),
+                Ok(status) => trace!(
+                    "bigmodel asr job {} pending: {:?}, attempt={}",
+                    self.id,
+                    status,
+                    poller.attempts()
+                ),
+                Err(e) if e.is_retryable() => warn!(
+                    "bigmodel asr job {} query failed, retrying: {}, attempt={}",
+                    self.id,
+                    e,
+                    poller.attempts()
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct AudioInfo {
+    pub duration: Option<i64>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct BigModelText {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub utterances: Vec<Utterance>,
+    pub additions: Option<serde_json::Value>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct BigModelResult {
+    pub audio_info: Option<AudioInfo>,
+    #[serde(default)]
+    pub result: BigModelText,
+}
+
+#[derive(Debug, Clone, SmartDefault)]
+pub struct BigModelRequestBuilder {
+    pub appid: Option<String>,
+    pub token: Option<String>,
+    #[default(RESOURCE_ID.to_string())]
+    pub resource_id: String,
+    pub uid: Option<String>,
+    pub url: Option<String>,
+    pub format: Option<String>,
+    pub codec: Option<String>,
+    pub rate: Option<i32>,
+    pub bits: Option<i32>,
+    pub channel: Option<i32>,
+    pub language: Option<String>,
+    pub options: BigModelOptions,
+    pub callback: Option<String>,
+    pub callback_data: Option<String>,
+}
+
+macro_rules! impl_options {
+    ($builder: ident) => {
+        impl $builder {
+            pub fn resource_id(mut self, resource_id: impl Into<String>) -> Self {
+                self.resource_id = resource_id.into();
+                self
+            }
+
+            pub fn enable_itn(mut self, enable: bool) -> Self {
+                self.options.enable_itn = Some(enable);
+                self
+            }
+
+            pub fn enable_punc(mut self, enable: bool) -> Self {
+                self.options.enable_punc = Some(enable);
+                self
+            }
+
+            pub fn enable_ddc(mut self, enable: bool) -> Self {
+                self.options.enable_ddc = Some(enable);
+                self
+            }
+
+            pub fn enable_speaker_info(mut self, enable: bool) -> Self {
+                self.options.enable_speaker_info = Some(enable);
+                self
+            }
+
+            pub fn enable_channel_split(mut self, enable: bool) -> Self {
+                self.options.enable_channel_split = Some(enable);
+                self
+            }
+
+            pub fn show_utterances(mut self, enable: bool) -> Self {
+                self.options.show_utterances = Some(enable);
+                self
+            }
+
+            pub fn vad_segment(mut self, enable: bool) -> Self {
+                self.options.vad_segment = Some(enable);
+                self
+            }
+
+            pub fn boosting_table_name(mut self, name: impl Into<String>) -> Self {
+                self.options
+                    .corpus
+                    .get_or_insert_with(Default::default)
+                    .boosting_table_name = Some(name.into());
+                self
+            }
+
+            pub fn correct_table_name(mut self, name: impl Into<String>) -> Self {
+                self.options
+                    .corpus
+                    .get_or_insert_with(Default::default)
+                    .correct_table_name = Some(name.into());
+                self
+            }
+
+            pub fn hotwords<S: Into<String>>(
+                mut self,
+                words: impl IntoIterator<Item = S>,
+            ) -> Self {
+                let hotwords = words
+                    .into_iter()
+                    .map(|w| serde_json::json!({ "word": w.into() }))
+                    .collect::<Vec<_>>();
+                self.options
+                    .corpus
+                    .get_or_insert_with(Default::default)
+                    .context = Some(serde_json::json!({ "hotwords": hotwords }).to_string());
+                self
+            }
+        }
+    };
+}
+
+pub(crate) use impl_options;
+
+impl_options!(BigModelRequestBuilder);
+
+impl BigModelRequestBuilder {
+    pub fn build(self) -> Result<BigModelRequest> {
+        let Self {
+            appid,
+            token,
+            resource_id,
+            uid,
+            url,
+            format,
+            codec,
+            rate,
+            bits,
+            channel,
+            language,
+            options,
+            callback,
+            callback_data,
+        } = self;
+
+        Ok(BigModelRequest {
+            appid: appid.ok_or(Error::BigModelRequestBuild("appid"))?,
+            token: token.ok_or(Error::BigModelRequestBuild("token"))?,
+            resource_id,
+            user: User {
+                uid: uid.ok_or(Error::BigModelRequestBuild("uid"))?,
+            },
+            audio: BigModelAudio {
+                url: Some(url.ok_or(Error::BigModelRequestBuild("url"))?),
+                data: None,
+                format,
+                codec,
+                rate,
+                bits,
+                channel,
+                language,
+            },
+            request: options,
+            callback,
+            callback_data,
+        })
+    }
+}
+
+macro_rules! impl_with {
+    ($param: ident, $typ:ty) => {
+        impl BigModelRequestBuilder {
+            pub fn $param(mut self, $param: impl Into<$typ>) -> Self {
+                self.$param = Some($param.into());
+                self
+            }
+        }
+    };
+}
+
+impl_with!(appid, String);
+impl_with!(token, String);
+impl_with!(uid, String);
+impl_with!(url, String);
+impl_with!(format, String);
+impl_with!(codec, String);
+impl_with!(rate, i32);
+impl_with!(bits, i32);
+impl_with!(channel, i32);
+impl_with!(language, String);
+impl_with!(callback, String);
+impl_with!(callback_data, String);
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_bigmodel_submit_query() -> Result<()> {
+    use crate::transport::MockResponse;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    for _ in 0..64 {
+        let id = new_request_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert!(matches!(&id[19..20], "8" | "9" | "a" | "b"));
+        assert!(id.split('-').map(str::len).eq([8, 4, 4, 4, 12].into_iter()));
+    }
+
+    let queries = AtomicUsize::new(0);
+    let client = Client::builder()
+        .access_token("bearer")
+        .transport(crate::transport::json_mock(move |req| {
+            let headers = req.headers();
+            assert_eq!(headers[X_API_APP_KEY], "100");
+            assert_eq!(headers[X_API_ACCESS_KEY], "token");
+            assert_eq!(headers[X_API_RESOURCE_ID], RESOURCE_ID);
+            assert_eq!(headers[X_API_REQUEST_ID].len(), 36);
+            assert!(!headers.contains_key(header::AUTHORIZATION));
+            let (status, body) = match req.url().path() {
+                "/api/v3/auc/bigmodel/submit" => {
+                    let body = crate::transport::request_json(req);
+                    assert_eq!(body["request"]["model_name"], "bigmodel");
+                    assert_eq!(body["request"]["enable_itn"], true);
+                    assert_eq!(
+                        body["request"]["corpus"]["context"],
+                        r#"{"hotwords":[{"word":"火山引擎"}]}"#
+                    );
+                    assert!(body.get("appid").is_none());
+                    ("20000000", serde_json::json!({}))
+                }
+                _ => match queries.fetch_add(1, Ordering::SeqCst) {
+                    0 => ("20000002", serde_json::json!({})),
+                    1 => ("20000001", serde_json::json!({})),
+                    _ => (
+                        "20000000",
+                        serde_json::json!({
+                            "audio_info": {"duration": 3696},
+                            "result": {
+                                "text": "火山引擎",
+                                "utterances": [{
+                                    "definite": true,
+                                    "start_time": 0,
+                                    "end_time": 1705,
+                                    "text": "火山引擎",
+                                    "words": [{"blank_duration": 0, "start_time": 740, "end_time": 1020, "text": "火"}],
+                                    "additions": {"speaker": "1"}
+                                }]
+                            }
+                        }),
+                    ),
+                },
+            };
+            MockResponse::json(body)
+                .header(X_API_STATUS_CODE, status)
+                .header("x-api-message", "OK")
+        }))
+        .build()?;
+
+    let rep = BigModelRequest::builder()
+        .appid("100")
+        .token("token")
+        .uid("uid")
+        .url("https://example.com/a.mp3")
+        .enable_itn(true)
+        .hotwords(["火山引擎"])
+        .build()?
+        .call(&client)
+        .await?;
+
+    let result = rep
+        .waiting_result(&client, PollPolicy::fixed(std::time::Duration::ZERO))
+        .await?;
+    assert_eq!(result.audio_info.unwrap().duration, Some(3696));
+    assert_eq!(result.result.utterances[0].words[0].text, "火");
+    assert_eq!(
+        result.result.utterances[0]
+            .additions
+            .as_ref()
+            .unwrap()
+            .speaker
+            .as_deref(),
+        Some("1")
+    );
+
+    assert!(matches!(
+        BigModelRequest::builder().appid("100").build(),
+        Err(Error::BigModelRequestBuild("token"))
+    ));
+    assert!(BigModelStatus::from(45000151).is_failure());
+    Ok(())
+}
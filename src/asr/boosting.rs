@@ -0,0 +1,181 @@
+use crate::{
+    error::*,
+    openapi::{SelfLearningTable, SelfLearningTables},
+};
+use std::collections::HashSet;
+
+pub const MAX_BOOSTING_WORDS: usize = 1000;
+pub const MAX_BOOSTING_WORD_CHARS: usize = 10;
+pub const MAX_BOOSTING_WEIGHT: u8 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BoostingWord {
+    pub word: String,
+    pub weight: Option<u8>,
+}
+
+impl BoostingWord {
+    pub fn new(word: impl Into<String>) -> Self {
+        Self {
+            word: word.into(),
+            weight: None,
+        }
+    }
+
+    pub fn weight(mut self, weight: u8) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+}
+
+impl From<&str> for BoostingWord {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<String> for BoostingWord {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<S: Into<String>> From<(S, u8)> for BoostingWord {
+    fn from((word, weight): (S, u8)) -> Self {
+        Self::new(word).weight(weight)
+    }
+}
+
+pub fn validate_words(words: &[BoostingWord]) -> Result<()> {
+    if words.is_empty() {
+        return Err(Error::BoostingTable("word list is empty".into()));
+    }
+    if words.len() > MAX_BOOSTING_WORDS {
+        return Err(Error::BoostingTable(format!(
+            "{} words exceeds the limit of {}",
+            words.len(),
+            MAX_BOOSTING_WORDS
+        )));
+    }
+    let mut seen = HashSet::new();
+    for (i, w) in words.iter().enumerate() {
+        let word = w.word.trim();
+        if word.is_empty() || word.contains(['|', '\n', '\r']) {
+            return Err(Error::BoostingTable(format!(
+                "word #{} {:?} is empty or contains '|' or a newline",
+                i + 1,
+                w.word
+            )));
+        }
+        if word.chars().count() > MAX_BOOSTING_WORD_CHARS {
+            return Err(Error::BoostingTable(format!(
+                "word #{} {:?} is longer than {} characters",
+                i + 1,
+                w.word,
+                MAX_BOOSTING_WORD_CHARS
+            )));
+        }
+        if let Some(weight) = w.weight {
+            if weight == 0 || weight > MAX_BOOSTING_WEIGHT {
+                return Err(Error::BoostingTable(format!(
+                    "word #{} {:?} has weight {} outside 1..={}",
+                    i + 1,
+                    w.word,
+                    weight,
+                    MAX_BOOSTING_WEIGHT
+                )));
+            }
+        }
+        if !seen.insert(word) {
+            return Err(Error::BoostingTable(format!(
+                "word #{} {:?} is duplicated",
+                i + 1,
+                w.word
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn render_words(words: &[BoostingWord]) -> String {
+    words
+        .iter()
+        .map(|w| match w.weight {
+            Some(weight) => format!("{}|{}\n", w.word.trim(), weight),
+            None => format!("{}\n", w.word.trim()),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct BoostingTable {
+    #[serde(rename = "BoostingTableID")]
+    pub id: String,
+    #[serde(rename = "BoostingTableName")]
+    pub name: String,
+    pub word_count: i64,
+    pub word_size: i64,
+    pub preview: Vec<String>,
+    pub create_time: String,
+    pub update_time: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct BoostingTableList {
+    pub boosting_tables: Vec<BoostingTable>,
+    pub page_number: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+impl SelfLearningTable for BoostingTable {
+    type Entry = BoostingWord;
+    type List = BoostingTableList;
+
+    const KIND: &'static str = "Boosting";
+    const FILE_NAME: &'static str = "words.txt";
+
+    fn validate(entries: &[BoostingWord]) -> Result<()> {
+        validate_words(entries)
+    }
+
+    fn render(entries: &[BoostingWord]) -> String {
+        render_words(entries)
+    }
+}
+
+pub type BoostingTables = SelfLearningTables<BoostingTable>;
+
+#[cfg(test)]
+#[test]
+fn test_boosting_words() -> Result<()> {
+    use crate::asr::record::RecordAsrRequest;
+    use serde_json::{json, Value};
+
+    assert!(validate_words(&[BoostingWord::from("火山引擎")]).is_ok());
+    assert!(matches!(
+        validate_words(&[("火山引擎", 11).into()]),
+        Err(Error::BoostingTable(_))
+    ));
+    assert!(validate_words(&["a".into(), "a".into()]).is_err());
+    assert!(validate_words(&["一二三四五六七八九十一".into()]).is_err());
+    assert!(validate_words(&vec!["a".into(); MAX_BOOSTING_WORDS + 1]).is_err());
+    assert_eq!(
+        render_words(&[(" 火山引擎 ", 5).into(), BoostingWord::new("豆包")]),
+        "火山引擎|5\n豆包\n"
+    );
+
+    let req = RecordAsrRequest::builder()
+        .appid("100")
+        .token("token")
+        .cluster("cluster")
+        .uid("uid")
+        .url("https://example.com/a.wav")
+        .boosting_table_id("b1")
+        .build()?;
+    let req: Value = serde_json::to_value(req)?;
+    assert_eq!(req["request"], json!({"boosting_table_id": "b1"}));
+    Ok(())
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    asr::record::{AucStatus, RecordAsrResponse, RecordAsrResult},
+    client::Client,
+    error::*,
+    poll::PollPolicy,
+};
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+pub const CALLBACK_PATH: &str = "/api/v1/auc/callback";
+pub const CALLBACK_TOKEN: &str = "callback_token";
+
+type Outcome = Result<RecordAsrResult>;
+
+struct Pending {
+    waiters: HashMap<String, oneshot::Sender<Outcome>>,
+    arrived: HashMap<String, (Instant, Outcome)>,
+    ttl: Duration,
+    capacity: usize,
+    secret: Option<String>,
+}
+
+impl Default for Pending {
+    fn default() -> Self {
+        Self {
+            waiters: HashMap::new(),
+            arrived: HashMap::new(),
+            ttl: Duration::from_secs(600),
+            capacity: 1024,
+            secret: None,
+        }
+    }
+}
+
+impl Pending {
+    fn evict(&mut self) {
+        let ttl = self.ttl;
+        self.arrived.retain(|_, (at, _)| at.elapsed() < ttl);
+        while self.arrived.len() >= self.capacity.max(1) {
+            let Some(oldest) = self
+                .arrived
+                .iter()
+                .min_by_key(|(_, (at, _))| *at)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            warn!("dropping unclaimed record asr callback for job {}", oldest);
+            self.arrived.remove(&oldest);
+        }
+    }
+
+    fn authorized(&self, token: Option<&String>) -> bool {
+        match (&self.secret, token) {
+            (None, _) => true,
+            (Some(secret), Some(token)) => {
+                secret.len() == token.len()
+                    && secret
+                        .bytes()
+                        .zip(token.bytes())
+                        .fold(0, |acc, (a, b)| acc | (a ^ b))
+                        == 0
+            }
+            (Some(_), None) => false,
+        }
+    }
+}
+
+pub struct CallbackReceiver {
+    pub addr: SocketAddr,
+    pub public_url: String,
+    pending: Arc<Mutex<Pending>>,
+    handle: JoinHandle<()>,
+}
+
+impl CallbackReceiver {
+    pub async fn bind(addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        let router = Router::new()
+            .route(CALLBACK_PATH, post(receive))
+            .with_state(pending.clone());
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                tracing::error!("callback receiver stopped: {}", e);
+            }
+        });
+        info!("record asr callback receiver listening on {}", addr);
+        Ok(Self {
+            addr,
+            public_url: format!("http://{}", addr),
+            pending,
+            handle,
+        })
+    }
+
+    pub fn public_url(mut self, public_url: impl Into<String>) -> Self {
+        self.public_url = public_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn ttl(self, ttl: Duration) -> Self {
+        self.pending.lock().expect("callback lock").ttl = ttl;
+        self
+    }
+
+    pub fn capacity(self, capacity: usize) -> Self {
+        self.pending.lock().expect("callback lock").capacity = capacity;
+        self
+    }
+
+    pub fn secret(self, secret: impl Into<String>) -> Self {
+        self.pending.lock().expect("callback lock").secret = Some(secret.into());
+        self
+    }
+
+    pub fn callback_url(&self) -> String {
+        let pending = self.pending.lock().expect("callback lock");
+        match &pending.secret {
+            Some(secret) => {
+                let mut url = format!("{}{}", self.public_url, CALLBACK_PATH);
+                url.push('?');
+                url.push_str(
+                    &url::form_urlencoded::Serializer::new(String::new())
+                        .append_pair(CALLBACK_TOKEN, secret)
+                        .finish(),
+                );
+                url
+            }
+            None => format!("{}{}", self.public_url, CALLBACK_PATH),
+        }
+    }
+
+    pub fn register(&self, id: impl Into<String>) -> oneshot::Receiver<Outcome> {
+        let id = id.into();
+        let (tx, rx) = oneshot::channel();
+        let mut pending = self.pending.lock().expect("callback lock");
+        pending.evict();
+        match pending.arrived.remove(&id).map(|(_, outcome)| outcome) {
+            Some(outcome) => {
+                let _ = tx.send(outcome);
+            }
+            None => {
+                pending.waiters.insert(id, tx);
+            }
+        }
+        rx
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.lock().expect("callback lock").waiters.len()
+    }
+
+    pub fn unclaimed(&self) -> usize {
+        self.pending.lock().expect("callback lock").arrived.len()
+    }
+
+    pub async fn wait(
+        &self,
+        rep: RecordAsrResponse,
+        client: &Client,
+        fallback_after: Duration,
+        policy: impl Into<PollPolicy>,
+    ) -> Result<RecordAsrResult> {
+        let id = rep.id.clone();
+        let rx = self.register(&id);
+        let policy = policy.into();
+
+        let fallback = async {
+            tokio::time::sleep(fallback_after).await;
+            warn!(
+                "no callback for record asr job {} after {:?}, polling",
+                id, fallback_after
+            );
+            rep.waiting_result(client, policy).await
+        };
+
+        let result = tokio::select! {
+            outcome = rx => match outcome {
+                Ok(outcome) => outcome,
+                Err(_) => Err(Error::Cancelled { id: id.clone() }),
+            },
+            outcome = fallback => outcome,
+        };
+
+        self.pending
+            .lock()
+            .expect("callback lock")
+            .waiters
+            .remove(&id);
+
+        result
+    }
+}
+
+impl Drop for CallbackReceiver {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn parse(headers: &HeaderMap, body: &[u8]) -> Option<(String, Option<Outcome>)> {
+    let mut value: Value = serde_json::from_slice(body).ok()?;
+    let value = match value.get_mut("resp") {
+        Some(resp) => resp.take(),
+        None => value,
+    };
+    let id = value.get("id")?.as_str()?.to_string();
+    let code = value.get("code").and_then(|v| v.as_i64()).unwrap_or(-1) as i32;
+    let outcome = match AucStatus::from(code) {
+        AucStatus::Success => Some(serde_json::from_value(value).map_err(Error::from)),
+        AucStatus::Processing | AucStatus::Queued => None,
+        _ => Some(Err(ApiError::new(StatusCode::OK, headers, body).into())),
+    };
+    Some((id, outcome))
+}
+
+async fn receive(
+    State(pending): State<Arc<Mutex<Pending>>>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> (StatusCode, Json<Value>) {
+    if !pending
+        .lock()
+        .expect("callback lock")
+        .authorized(query.get(CALLBACK_TOKEN))
+    {
+        warn!("rejected record asr callback with invalid token");
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({"code": 1002, "message": "invalid callback token"})),
+        );
+    }
+    let Some((id, outcome)) = parse(&headers, &body) else {
+        warn!(
+            "invalid record asr callback: {}",
+            String::from_utf8_lossy(&body)
+        );
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({"code": 1001, "message": "invalid callback payload"})),
+        );
+    };
+    let Some(outcome) = outcome else {
+        info!("record asr job {} still in progress", id);
+        return (
+            StatusCode::OK,
+            Json(json!({"code": 0, "message": "Success"})),
+        );
+    };
+    info!("received record asr callback for job {}", id);
+    let mut pending = pending.lock().expect("callback lock");
+    match pending.waiters.remove(&id) {
+        Some(tx) => {
+            let _ = tx.send(outcome);
+        }
+        None => {
+            pending.evict();
+            pending.arrived.insert(id, (Instant::now(), outcome));
+        }
+    }
+    (
+        StatusCode::OK,
+        Json(json!({"code": 0, "message": "Success"})),
+    )
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_callback_receiver() -> Result<()> {
+    let receiver = CallbackReceiver::bind("127.0.0.1:0")
+        .await?
+        .secret("s3cret")
+        .capacity(2);
+    let rep = |id: &str| RecordAsrResponse {
+        code: 1000,
+        message: "Success".into(),
+        id: id.into(),
+        appid: "100".into(),
+        token: "token".into(),
+        cluster: "volc_auc_common".into(),
+    };
+
+    let client = Client::builder()
+        .transport(crate::transport::json_mock(|_| {
+            serde_json::json!({"resp": {"id": "polled", "code": 1000, "message": "Success",
+                "additions": {}, "utterances": []}})
+        }))
+        .build()?;
+
+    let http = reqwest::Client::new();
+    let url = receiver.callback_url();
+    assert!(url.ends_with("/api/v1/auc/callback?callback_token=s3cret"));
+
+    let forged = http
+        .post(format!("http://{}{}", receiver.addr, CALLBACK_PATH))
+        .body(r#"{"id": "job", "code": 1000, "message": "Success"}"#)
+        .send()
+        .await?;
+    assert_eq!(forged.status(), 401);
+    assert_eq!(receiver.unclaimed(), 0);
+    let callback = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        http.post(&url)
+            .body(
+                r#"{"resp": {"id": "job", "code": 1000, "message": "Success", "text": "你好",
+                    "additions": {}, "utterances": []}}"#,
+            )
+            .send()
+            .await
+    };
+    let (result, sent) = tokio::join!(
+        receiver.wait(rep("job"), &client, Duration::from_secs(30), Duration::ZERO),
+        callback
+    );
+    assert_eq!(sent?.status(), 200);
+    assert_eq!(result?.text.as_deref(), Some("你好"));
+
+    for body in [
+        r#"{"id": "failed", "code": 2000, "message": "processing"}"#,
+        r#"{"id": "failed", "code": 1013, "message": "audio is silent"}"#,
+    ] {
+        http.post(&url).body(body).send().await?;
+    }
+    let e = receiver
+        .wait(
+            rep("failed"),
+            &client,
+            Duration::from_secs(30),
+            Duration::ZERO,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(e.api().and_then(|e| e.code.as_deref()), Some("1013"));
+
+    let result = receiver
+        .wait(
+            rep("polled"),
+            &client,
+            Duration::from_millis(10),
+            Duration::ZERO,
+        )
+        .await?;
+    assert_eq!(result.id, "polled");
+    assert_eq!(receiver.pending(), 0);
+
+    for id in ["a", "b", "c"] {
+        http.post(&url)
+            .body(format!(
+                r#"{{"id": "{}", "code": 1013, "message": "silent"}}"#,
+                id
+            ))
+            .send()
+            .await?;
+    }
+    assert_eq!(receiver.unclaimed(), 2);
+    Ok(())
+}
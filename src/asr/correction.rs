@@ -0,0 +1,169 @@
+use crate::{
+    error::*,
+    openapi::{SelfLearningTable, SelfLearningTables},
+};
+use std::collections::HashSet;
+
+pub const MAX_CORRECT_RULES: usize = 1000;
+pub const MAX_CORRECT_WORD_CHARS: usize = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CorrectRule {
+    pub from: String,
+    pub to: String,
+}
+
+impl CorrectRule {
+    pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+}
+
+impl<F: Into<String>, T: Into<String>> From<(F, T)> for CorrectRule {
+    fn from((from, to): (F, T)) -> Self {
+        Self::new(from, to)
+    }
+}
+
+pub fn validate_rules(rules: &[CorrectRule]) -> Result<()> {
+    if rules.is_empty() {
+        return Err(Error::CorrectTable("rule list is empty".into()));
+    }
+    if rules.len() > MAX_CORRECT_RULES {
+        return Err(Error::CorrectTable(format!(
+            "{} rules exceeds the limit of {}",
+            rules.len(),
+            MAX_CORRECT_RULES
+        )));
+    }
+    let mut seen = HashSet::new();
+    for (i, rule) in rules.iter().enumerate() {
+        for word in [&rule.from, &rule.to] {
+            let word = word.trim();
+            if word.is_empty() || word.contains(['|', '\n', '\r']) {
+                return Err(Error::CorrectTable(format!(
+                    "rule #{} {:?} -> {:?} has an empty word or contains '|' or a newline",
+                    i + 1,
+                    rule.from,
+                    rule.to
+                )));
+            }
+            if word.chars().count() > MAX_CORRECT_WORD_CHARS {
+                return Err(Error::CorrectTable(format!(
+                    "rule #{} {:?} is longer than {} characters",
+                    i + 1,
+                    word,
+                    MAX_CORRECT_WORD_CHARS
+                )));
+            }
+        }
+        if rule.from.trim() == rule.to.trim() {
+            return Err(Error::CorrectTable(format!(
+                "rule #{} replaces {:?} with itself",
+                i + 1,
+                rule.from
+            )));
+        }
+        if !seen.insert(rule.from.trim()) {
+            return Err(Error::CorrectTable(format!(
+                "rule #{} {:?} is duplicated",
+                i + 1,
+                rule.from
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn render_rules(rules: &[CorrectRule]) -> String {
+    rules
+        .iter()
+        .map(|r| format!("{}|{}\n", r.from.trim(), r.to.trim()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct CorrectTable {
+    #[serde(rename = "CorrectTableID")]
+    pub id: String,
+    #[serde(rename = "CorrectTableName")]
+    pub name: String,
+    pub word_count: i64,
+    pub word_size: i64,
+    pub preview: Vec<String>,
+    pub create_time: String,
+    pub update_time: String,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct CorrectTableList {
+    pub correct_tables: Vec<CorrectTable>,
+    pub page_number: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+impl SelfLearningTable for CorrectTable {
+    type Entry = CorrectRule;
+    type List = CorrectTableList;
+
+    const KIND: &'static str = "Correct";
+    const FILE_NAME: &'static str = "rules.txt";
+
+    fn validate(entries: &[CorrectRule]) -> Result<()> {
+        validate_rules(entries)
+    }
+
+    fn render(entries: &[CorrectRule]) -> String {
+        render_rules(entries)
+    }
+}
+
+pub type CorrectTables = SelfLearningTables<CorrectTable>;
+
+#[cfg(test)]
+#[test]
+fn test_correct_rules() -> Result<()> {
+    use crate::asr::{
+        record::RecordAsrRequest,
+        subtitle::{SubtitleRequest, SubtitleSource},
+    };
+    use serde_json::{json, Value};
+
+    assert!(validate_rules(&[("火山音箭", "火山引擎").into()]).is_ok());
+    assert!(matches!(
+        validate_rules(&[("豆包", "豆包").into()]),
+        Err(Error::CorrectTable(_))
+    ));
+    assert!(validate_rules(&[("a", "b").into(), ("a", "c").into()]).is_err());
+    assert!(validate_rules(&[("a|b", "c").into()]).is_err());
+
+    assert_eq!(
+        render_rules(&[(" 火山音箭 ", "火山引擎").into()]),
+        "火山音箭|火山引擎\n"
+    );
+
+    let req = RecordAsrRequest::builder()
+        .appid("100")
+        .token("token")
+        .cluster("cluster")
+        .uid("uid")
+        .url("https://example.com/a.wav")
+        .correct_table_id("c1")
+        .build()?;
+    let req: Value = serde_json::to_value(req)?;
+    assert_eq!(req["request"], json!({"correct_table_id": "c1"}));
+
+    let req = SubtitleRequest::builder()
+        .appid("100")
+        .correct_table_name("brands")
+        .source(SubtitleSource::Url("https://example.com/a.wav".into()))
+        .build()?;
+    assert_eq!(req.params["correct_table_name"], "brands");
+    Ok(())
+}
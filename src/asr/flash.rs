@@ -0,0 +1,250 @@
+use std::path::Path;
+
+use crate::{
+    asr::{
+        bigmodel::{
+            api_client, api_headers, impl_options, new_request_id, BigModelAudio, BigModelOptions,
+            BigModelResult, BigModelStatus,
+        },
+        record::User,
+    },
+    client::{Client, RawResponse},
+    error::*,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::Method;
+use reqwest::Body;
+use serde_with::skip_serializing_none;
+use smart_default::SmartDefault;
+use tracing::error;
+
+pub const FLASH_RESOURCE_ID: &str = "volc.bigasr.auc_turbo";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlashAudio {
+    Url(String),
+    Data(Vec<u8>),
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct FlashRequest {
+    #[serde(skip)]
+    pub appid: String,
+    #[serde(skip)]
+    pub token: String,
+    #[serde(skip)]
+    pub resource_id: String,
+    pub user: User,
+    pub audio: BigModelAudio,
+    pub request: BigModelOptions,
+}
+
+impl FlashRequest {
+    pub fn builder() -> FlashRequestBuilder {
+        FlashRequestBuilder::default()
+    }
+
+    pub async fn call(&self, client: &Client) -> Result<BigModelResult> {
+        let id = new_request_id();
+        let rep = api_client(client)
+            .call(
+                Method::POST,
+                "/api/v3/auc/bigmodel/recognize/flash",
+                vec![],
+                api_headers(&self.appid, &self.token, &self.resource_id, &id)?,
+                Some(Body::from(serde_json::to_string(self)?)),
+            )
+            .await?;
+
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
+
+        match BigModelStatus::from_response(&raw) {
+            Some(BigModelStatus::Success) => raw.json(),
+            _ => {
+                let e = raw.api_error();
+                error!("flash asr request {} failed: {}", id, e);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, SmartDefault)]
+pub struct FlashRequestBuilder {
+    pub appid: Option<String>,
+    pub token: Option<String>,
+    #[default(FLASH_RESOURCE_ID.to_string())]
+    pub resource_id: String,
+    pub uid: Option<String>,
+    pub audio: Option<FlashAudio>,
+    pub format: Option<String>,
+    pub codec: Option<String>,
+    pub rate: Option<i32>,
+    pub bits: Option<i32>,
+    pub channel: Option<i32>,
+    pub language: Option<String>,
+    pub options: BigModelOptions,
+}
+
+impl_options!(FlashRequestBuilder);
+
+impl FlashRequestBuilder {
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.audio = Some(FlashAudio::Url(url.into()));
+        self
+    }
+
+    pub fn data(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.audio = Some(FlashAudio::Data(data.into()));
+        self
+    }
+
+    pub fn file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = self.format.clone().or_else(|| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_string())
+        });
+        Ok(Self {
+            audio: Some(FlashAudio::Data(std::fs::read(path)?)),
+            format,
+            ..self
+        })
+    }
+
+    pub fn build(self) -> Result<FlashRequest> {
+        let Self {
+            appid,
+            token,
+            resource_id,
+            uid,
+            audio,
+            format,
+            codec,
+            rate,
+            bits,
+            channel,
+            language,
+            options,
+        } = self;
+
+        let (url, data) = match audio.ok_or(Error::FlashRequestBuild("audio"))? {
+            FlashAudio::Url(url) => (Some(url), None),
+            FlashAudio::Data(data) => (None, Some(STANDARD.encode(data))),
+        };
+
+        Ok(FlashRequest {
+            appid: appid.ok_or(Error::FlashRequestBuild("appid"))?,
+            token: token.ok_or(Error::FlashRequestBuild("token"))?,
+            resource_id,
+            user: User {
+                uid: uid.ok_or(Error::FlashRequestBuild("uid"))?,
+            },
+            audio: BigModelAudio {
+                url,
+                data,
+                format,
+                codec,
+                rate,
+                bits,
+                channel,
+                language,
+            },
+            request: options,
+        })
+    }
+}
+
+macro_rules! impl_with {
+    ($param: ident, $typ:ty) => {
+        impl FlashRequestBuilder {
+            pub fn $param(mut self, $param: impl Into<$typ>) -> Self {
+                self.$param = Some($param.into());
+                self
+            }
+        }
+    };
+}
+
+impl_with!(appid, String);
+impl_with!(token, String);
+impl_with!(uid, String);
+impl_with!(format, String);
+impl_with!(codec, String);
+impl_with!(rate, i32);
+impl_with!(bits, i32);
+impl_with!(channel, i32);
+impl_with!(language, String);
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_flash_recognize() -> Result<()> {
+    use crate::{
+        asr::bigmodel::{X_API_RESOURCE_ID, X_API_STATUS_CODE},
+        caption::Captions,
+        transport::MockResponse,
+    };
+
+    let client = Client::builder()
+        .access_token("bearer")
+        .transport(crate::transport::json_mock(|req| {
+            assert_eq!(req.url().path(), "/api/v3/auc/bigmodel/recognize/flash");
+            assert!(!req.headers().contains_key(http::header::AUTHORIZATION));
+            assert_eq!(req.headers()[X_API_RESOURCE_ID], FLASH_RESOURCE_ID);
+            let body = crate::transport::request_json(req);
+            match body["audio"]["data"].as_str() {
+                Some("SUQz") => MockResponse::json(serde_json::json!({
+                    "audio_info": {"duration": 1200},
+                    "result": {
+                        "text": "你好",
+                        "utterances": [{
+                            "start_time": 0,
+                            "end_time": 1200,
+                            "text": "你好",
+                            "words": [
+                                {"start_time": 0, "end_time": 600, "text": "你"},
+                                {"start_time": 600, "end_time": 1200, "text": "好"}
+                            ]
+                        }]
+                    }
+                }))
+                .header(X_API_STATUS_CODE, "20000000"),
+                _ => MockResponse::json(serde_json::json!({}))
+                    .header(X_API_STATUS_CODE, "45000151")
+                    .header("x-api-message", "invalid audio format"),
+            }
+        }))
+        .build()?;
+
+    let result = FlashRequest::builder()
+        .appid("100")
+        .token("token")
+        .uid("uid")
+        .data(b"ID3".to_vec())
+        .enable_punc(true)
+        .build()?
+        .call(&client)
+        .await?;
+    assert_eq!(result.result.text, "你好");
+    assert_eq!(result.result.utterances[0].words.len(), 2);
+    assert_eq!(
+        result.to_srt(),
+        "1\n00:00:00,000 --> 00:00:01,200\n你好\n\n"
+    );
+
+    let e = FlashRequest::builder()
+        .appid("100")
+        .token("token")
+        .uid("uid")
+        .url("https://example.com/a.wav")
+        .build()?
+        .call(&client)
+        .await
+        .unwrap_err();
+    let e = e.api().unwrap();
+    assert_eq!(e.code.as_deref(), Some("45000151"));
+    assert_eq!(e.message, "invalid audio format");
+    Ok(())
+}
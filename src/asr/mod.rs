@@ -1,2 +1,10 @@
 pub mod subtitle;
-pub mod record;
\ No newline at end of file
+pub mod align;
+pub mod record;
+pub mod batch;
+pub mod bigmodel;
+pub mod boosting;
+pub mod correction;
+pub mod flash;
+#[cfg(feature = "callback")]
+pub mod callback;
\ No newline at end of file
@@ -1,10 +1,105 @@
+use std::path::Path;
 use std::time::Duration;
 
-use crate::{client::Client, error::*, types::*};
+use crate::{
+    client::{Client, RawResponse},
+    error::*,
+    poll::{watch, JobEvent, JobStatus, PollPolicy},
+    tos::TosClient,
+    types::*,
+};
+use futures::Stream;
 use http::{header, HeaderValue, Method};
 use reqwest::Body;
 use serde_with::skip_serializing_none;
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AucStatus {
+    Success,
+    InvalidParams,
+    AccessDenied,
+    RateLimited,
+    QuotaExceeded,
+    ServerBusy,
+    AudioTooLong,
+    AudioTooLarge,
+    InvalidAudioFormat,
+    SilentAudio,
+    WaitingTimeout,
+    ProcessingTimeout,
+    RecognitionError,
+    UnknownError,
+    Processing,
+    Queued,
+    Other(i32),
+}
+
+impl AucStatus {
+    pub fn code(&self) -> i32 {
+        match self {
+            AucStatus::Success => 1000,
+            AucStatus::InvalidParams => 1001,
+            AucStatus::AccessDenied => 1002,
+            AucStatus::RateLimited => 1003,
+            AucStatus::QuotaExceeded => 1004,
+            AucStatus::ServerBusy => 1005,
+            AucStatus::AudioTooLong => 1010,
+            AucStatus::AudioTooLarge => 1011,
+            AucStatus::InvalidAudioFormat => 1012,
+            AucStatus::SilentAudio => 1013,
+            AucStatus::WaitingTimeout => 1020,
+            AucStatus::ProcessingTimeout => 1021,
+            AucStatus::RecognitionError => 1022,
+            AucStatus::UnknownError => 1099,
+            AucStatus::Processing => 2000,
+            AucStatus::Queued => 2001,
+            AucStatus::Other(code) => *code,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, AucStatus::Success)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, AucStatus::Processing | AucStatus::Queued)
+    }
+
+    pub fn is_failure(&self) -> bool {
+        !self.is_success() && !self.is_pending()
+    }
+}
+
+impl From<i32> for AucStatus {
+    fn from(value: i32) -> Self {
+        match value {
+            1000 => AucStatus::Success,
+            1001 => AucStatus::InvalidParams,
+            1002 => AucStatus::AccessDenied,
+            1003 => AucStatus::RateLimited,
+            1004 => AucStatus::QuotaExceeded,
+            1005 => AucStatus::ServerBusy,
+            1010 => AucStatus::AudioTooLong,
+            1011 => AucStatus::AudioTooLarge,
+            1012 => AucStatus::InvalidAudioFormat,
+            1013 => AucStatus::SilentAudio,
+            1020 => AucStatus::WaitingTimeout,
+            1021 => AucStatus::ProcessingTimeout,
+            1022 => AucStatus::RecognitionError,
+            1099 => AucStatus::UnknownError,
+            2000 => AucStatus::Processing,
+            2001 => AucStatus::Queued,
+            code => AucStatus::Other(code),
+        }
+    }
+}
+
+impl From<AucStatus> for i32 {
+    fn from(value: AucStatus) -> Self {
+        value.code()
+    }
+}
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
@@ -21,64 +116,98 @@ pub struct RecordAsrResponse {
 }
 
 impl RecordAsrResponse {
-    pub async fn waiting_result(self, client: &Client, retry: Duration) -> Result<RecordAsrResult> {
-        let Self {
-            id,
-            appid,
-            token,
-            cluster,
-            ..
-        } = self;
+    pub fn status(&self) -> AucStatus {
+        AucStatus::from(self.code)
+    }
 
+    pub async fn query(&self, client: &Client) -> Result<JobStatus<RecordAsrResult>> {
         let body = serde_json::json!( {
-            "appid": appid,
-            "token": token,
-            "cluster": cluster,
-            "id": id,
+            "appid": self.appid,
+            "token": self.token,
+            "cluster": self.cluster,
+            "id": self.id,
         });
 
-        for l in serde_json::to_string_pretty(&body)?.lines() {
-            trace!("REQ: {}", l);
-        }
-
-        let rep = loop {
-            let rep = client
-                .call(
-                    Method::POST,
-                    "/api/v1/auc/query",
-                    vec![],
-                    vec![(
-                        header::CONTENT_TYPE,
-                        HeaderValue::from_str("application/json")?,
-                    )],
-                    Some(Body::from(serde_json::to_string(&body)?)),
-                )
-                .await?;
+        let rep = client
+            .call_idempotent(
+                Method::POST,
+                "/api/v1/auc/query",
+                vec![],
+                vec![(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_str("application/json")?,
+                )],
+                Some(Body::from(serde_json::to_string(&body)?)),
+            )
+            .await?;
 
-            let rep = rep.bytes().await?;
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
 
-            let mut rep: serde_json::Value = serde_json::from_slice(rep.as_ref())?;
+        let mut rep: serde_json::Value = raw.json()?;
 
-            let rep = rep.get_mut("resp").ok_or(Error::RecordAsrResponse)?.take();
+        let rep = rep
+            .get_mut("resp")
+            .map(serde_json::Value::take)
+            .ok_or_else(|| raw.api_error())?;
 
-            for l in serde_json::to_string_pretty(&rep)?.lines() {
-                trace!("REP: {}", l);
-            }
+        let code = rep
+            .get("code")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(|| raw.api_error())? as i32;
 
-            match rep.get("code").and_then(|v| v.as_i64()) {
-                Some(1000) => break rep,
-                _ => {
-                    tokio::time::sleep(retry).await;
-                    continue;
+        match AucStatus::from(code) {
+            AucStatus::Success => Ok(JobStatus::Done(raw.parse(rep)?)),
+            AucStatus::Queued => Ok(JobStatus::Queued),
+            AucStatus::Processing => Ok(JobStatus::Processing),
+            _ => {
+                let e = raw.api_error();
+                if !e.is_retryable() {
+                    error!("record asr job {} failed: {}", self.id, e);
                 }
+                Err(e)
             }
-        };
-
-        for l in serde_json::to_string_pretty(&rep)?.lines() {
-            trace!("REP: {}", l);
         }
+    }
 
-        Ok(serde_json::from_value(rep)?)
+    pub fn events(
+        &self,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> impl Stream<Item = JobEvent<RecordAsrResult>> {
+        let (rep, client) = (self.clone(), client.clone());
+        watch(policy.into().poller(&self.id), move || {
+            let (rep, client) = (rep.clone(), client.clone());
+            async move { rep.query(&client).await }
+        })
+    }
+
+    pub async fn waiting_result(
+        self,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> Result<RecordAsrResult> {
+        let mut poller = policy.into().poller(&self.id);
+
+        loop {
+            poller.next_attempt().await?;
+
+            match poller.attempt(self.query(client)).await {
+                Ok(JobStatus::Done(rep)) => return Ok(rep),
+                Ok(status) => trace!(
+                    "record asr job {} pending: {:?}, attempt={}",
+                    self.id,
+                    status,
+                    poller.attempts()
+                ),
+                Err(e) if e.is_retryable() => warn!(
+                    "record asr job {} query failed, retrying: {}, attempt={}",
+                    self.id,
+                    e,
+                    poller.attempts()
+                ),
+                Err(e) => return Err(e),
+            }
+        }
     }
 }
 
@@ -114,28 +243,20 @@ impl RecordAsrRequest {
             )
             .await?;
 
-        let status = rep.status();
-
-        let body = rep.bytes().await?;
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
 
-        let mut body = serde_json::from_slice::<serde_json::Value>(body.as_ref())?;
+        let mut body: serde_json::Value = raw.json()?;
 
-        match status.is_success() {
-            true => {
-                for l in serde_json::to_string_pretty(&body)?.lines() {
-                    trace!("REP: {}", l);
-                }
-            }
-            false => {
-                for l in serde_json::to_string_pretty(&body)?.lines() {
-                    error!("REP: {}", l);
-                }
-            }
-        }
+        let rep = body
+            .get_mut("resp")
+            .map(serde_json::Value::take)
+            .ok_or_else(|| raw.api_error())?;
 
-        let rep = body.get_mut("resp").ok_or(Error::RecordAsrResponse)?.take();
+        let mut rep: RecordAsrResponse = raw.parse(rep)?;
 
-        let mut rep = serde_json::from_value::<RecordAsrResponse>(rep)?;
+        if rep.status().is_failure() {
+            return Err(raw.api_error());
+        }
 
         rep.appid = self.app.appid;
         rep.token = self.app.token;
@@ -175,6 +296,9 @@ pub struct Audio {
 pub struct Request {
     pub callback: Option<String>,
     pub boosting_table_name: Option<String>,
+    pub boosting_table_id: Option<String>,
+    pub correct_table_name: Option<String>,
+    pub correct_table_id: Option<String>,
 }
 
 #[skip_serializing_none]
@@ -206,6 +330,9 @@ pub struct RecordAsrRequestBuilder {
     pub channel: Option<i32>,
     pub callback: Option<String>,
     pub boosting_table_name: Option<String>,
+    pub boosting_table_id: Option<String>,
+    pub correct_table_name: Option<String>,
+    pub correct_table_id: Option<String>,
     pub language: Option<String>,
     pub use_itn: Option<Boolean>,
     pub use_punc: Option<Boolean>,
@@ -230,6 +357,9 @@ impl RecordAsrRequestBuilder {
             channel,
             callback,
             boosting_table_name,
+            boosting_table_id,
+            correct_table_name,
+            correct_table_id,
             language,
             use_itn,
             use_punc,
@@ -240,17 +370,17 @@ impl RecordAsrRequestBuilder {
         } = self;
 
         let app = App {
-            appid: appid.ok_or(Error::RecordRequestBuild)?,
-            token: token.ok_or(Error::RecordRequestBuild)?,
-            cluster: cluster.ok_or(Error::RecordRequestBuild)?,
+            appid: appid.ok_or(Error::RecordRequestBuild("appid"))?,
+            token: token.ok_or(Error::RecordRequestBuild("token"))?,
+            cluster: cluster.ok_or(Error::RecordRequestBuild("cluster"))?,
         };
 
         let user = User {
-            uid: uid.ok_or(Error::RecordRequestBuild)?,
+            uid: uid.ok_or(Error::RecordRequestBuild("uid"))?,
         };
 
         let audio = Audio {
-            url: url.ok_or(Error::RecordRequestBuild)?,
+            url: url.ok_or(Error::RecordRequestBuild("url"))?,
             format,
             codec,
             rate,
@@ -258,10 +388,18 @@ impl RecordAsrRequestBuilder {
             channel,
         };
 
-        let request = match callback.is_some() || boosting_table_name.is_some() {
+        let request = match callback.is_some()
+            || boosting_table_name.is_some()
+            || boosting_table_id.is_some()
+            || correct_table_name.is_some()
+            || correct_table_id.is_some()
+        {
             true => Some(Request {
                 callback,
                 boosting_table_name,
+                boosting_table_id,
+                correct_table_name,
+                correct_table_id,
             }),
             false => None,
         };
@@ -294,6 +432,26 @@ impl RecordAsrRequestBuilder {
             additions,
         })
     }
+
+    pub async fn upload(
+        self,
+        tos: &TosClient,
+        path: impl AsRef<Path>,
+        expires: Duration,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let url = tos.upload_audio(path, expires).await?;
+        let format = self.format.clone().or_else(|| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_string())
+        });
+        Ok(Self {
+            url: Some(url.to_string()),
+            format,
+            ..self
+        })
+    }
 }
 
 macro_rules! impl_with {
@@ -319,6 +477,9 @@ impl_with!(bits, i32);
 impl_with!(channel, i32);
 impl_with!(callback, String);
 impl_with!(boosting_table_name, String);
+impl_with!(boosting_table_id, String);
+impl_with!(correct_table_name, String);
+impl_with!(correct_table_id, String);
 impl_with!(language, String);
 impl_with!(use_itn, Boolean);
 impl_with!(use_punc, Boolean);
@@ -365,6 +526,125 @@ pub struct RecordAsrResult {
     pub utterances: Vec<Utterance>,
 }
 
+#[cfg(test)]
+#[test]
+fn test_auc_status_code() {
+    assert_eq!(AucStatus::from(1000), AucStatus::Success);
+    assert!(AucStatus::from(2000).is_pending());
+    assert!(AucStatus::from(2001).is_pending());
+    assert!(AucStatus::from(1013).is_failure());
+    assert_eq!(AucStatus::from(1234), AucStatus::Other(1234));
+    for code in [
+        1000, 1001, 1002, 1003, 1004, 1005, 1010, 1011, 1012, 1013, 1020, 1021, 1022, 1099, 2000,
+        2001,
+    ] {
+        assert_eq!(AucStatus::from(code).code(), code);
+    }
+}
+
+#[cfg(test)]
+fn mock_auc_client(codes: &'static [i32]) -> Result<Client> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let queries = AtomicUsize::new(0);
+    Client::builder()
+        .transport(crate::transport::json_mock(move |req| {
+            let body = crate::transport::request_json(req);
+            match req.url().path() {
+                "/api/v1/auc/submit" => {
+                    serde_json::json!({"resp": {"id": "job", "code": 1000, "message": "ok"}})
+                }
+                "/api/v1/auc/query" => {
+                    assert_eq!(body["id"], "job");
+                    assert_eq!(body["token"], "token");
+                    let n = queries.fetch_add(1, Ordering::SeqCst);
+                    serde_json::json!({"resp": {
+                        "id": "job",
+                        "code": codes[n.min(codes.len() - 1)],
+                        "message": "",
+                        "additions": {},
+                        "utterances": [],
+                    }})
+                }
+                path => unreachable!("{}", path),
+            }
+        }))
+        .build()
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_record_asr_retry_throttled_query() -> Result<()> {
+    let request = RecordAsrRequest::builder()
+        .appid("100")
+        .token("token")
+        .cluster("volc_auc_common")
+        .uid("uid")
+        .url("https://example.com/a.mp3")
+        .build()?;
+    let policy = crate::poll::PollPolicy::fixed(Duration::ZERO);
+
+    let client = mock_auc_client(&[1003, 2000, 1005, 1000])?;
+    let result = request
+        .clone()
+        .call(&client)
+        .await?
+        .waiting_result(&client, policy.clone())
+        .await?;
+    assert_eq!(result.code, 1000);
+
+    let client = mock_auc_client(&[2001, 1013])?;
+    let e = request
+        .call(&client)
+        .await?
+        .waiting_result(&client, policy)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        e.api().and_then(|e| e.auc_status()),
+        Some(AucStatus::SilentAudio)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_record_asr_events() -> Result<()> {
+    use futures::StreamExt;
+
+    let rep = RecordAsrResponse {
+        code: 1000,
+        message: "ok".into(),
+        id: "job".into(),
+        appid: "100".into(),
+        token: "token".into(),
+        cluster: "volc_auc_common".into(),
+    };
+    let policy = crate::poll::PollPolicy::fixed(Duration::ZERO);
+
+    let client = mock_auc_client(&[2001, 2000, 2000, 1000])?;
+    let events = rep
+        .events(&client, policy.clone())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(events.len(), 5);
+    assert!(matches!(&events[0], JobEvent::Submitted { id } if id == "job"));
+    assert!(matches!(events[1], JobEvent::Queued { attempt: 1 }));
+    assert!(matches!(events[2], JobEvent::Processing { attempt: 2 }));
+    assert!(matches!(events[3], JobEvent::Processing { attempt: 3 }));
+    assert!(matches!(&events[4], JobEvent::Succeeded(r) if r.code == 1000));
+
+    let client = mock_auc_client(&[2000, 1013])?;
+    let events = rep.events(&client, policy).collect::<Vec<_>>().await;
+    assert_eq!(events.len(), 3);
+    assert!(matches!(events[1], JobEvent::Processing { attempt: 1 }));
+    assert!(matches!(
+        &events[2],
+        JobEvent::Failed(e) if e.api().and_then(|e| e.auc_status()) == Some(AucStatus::SilentAudio)
+    ));
+    Ok(())
+}
+
 #[cfg(test)]
 #[tokio::test]
 async fn test_record_asr_ok() -> Result<()> {
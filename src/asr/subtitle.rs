@@ -1,6 +1,12 @@
 use std::collections::HashMap;
 
-use crate::{client::Client, error::*, types::*};
+use crate::{
+    client::{Client, RawResponse},
+    error::*,
+    poll::{watch, JobEvent, JobStatus, PollPolicy},
+    types::*,
+};
+use futures::Stream;
 use http::{header, HeaderValue, Method};
 use reqwest::Body;
 use serde_json::{json, Value};
@@ -41,6 +47,8 @@ impl_with_params!(
     use_ddc,
     boosting_table_id,
     boosting_table_name,
+    correct_table_id,
+    correct_table_name,
     asr_appid,
     with_speaker_info
 );
@@ -53,9 +61,9 @@ impl SubtitleRequestBuilder {
 
     pub fn build(self) -> Result<SubtitleRequest> {
         let Self { params, source } = self;
-        let source = source.ok_or(Error::SubtitleRequestBuild)?;
+        let source = source.ok_or(Error::SubtitleRequestBuild("source"))?;
         if !params.contains_key("appid") {
-            return Err(Error::SubtitleRequestBuild);
+            return Err(Error::SubtitleRequestBuild("appid"));
         }
         Ok(SubtitleRequest { params, source })
     }
@@ -74,7 +82,7 @@ impl SubtitleRequest {
             .collect::<Vec<_>>();
         let rep = match source {
             SubtitleSource::Binary { typ, data } => {
-                let rep = client
+                client
                     .call(
                         Method::POST,
                         "/api/v1/vc/submit",
@@ -85,16 +93,11 @@ impl SubtitleRequest {
                         )],
                         Some(Body::from(data.to_owned())),
                     )
-                    .await?; //.error_for_status()?;
-                let val: Value = serde_json::from_slice(rep.bytes().await?.as_ref())?;
-                for l in serde_json::to_string_pretty(&val)?.lines() {
-                    trace!("REP: {}", l);
-                }
-                serde_json::from_value(val)?
+                    .await?
             }
             SubtitleSource::Url(url) => {
                 let body = json!({"url": url});
-                let rep = client
+                client
                     .call(
                         Method::POST,
                         "/api/v1/vc/submit",
@@ -106,10 +109,13 @@ impl SubtitleRequest {
                         Some(Body::from(serde_json::to_string(&body)?)),
                     )
                     .await?
-                    .error_for_status()?;
-                serde_json::from_slice(rep.bytes().await?.as_ref())?
             }
         };
+        let raw = RawResponse::read(rep).await?.error_for_status()?;
+        let rep: SubtitleResponse = raw.json()?;
+        if rep.code != 0 {
+            return Err(raw.api_error());
+        }
         Ok(rep)
     }
 }
@@ -122,32 +128,95 @@ pub struct SubtitleResponse {
 }
 
 impl SubtitleResponse {
+    pub async fn query(
+        &self,
+        appid: impl AsRef<str>,
+        client: &Client,
+    ) -> Result<JobStatus<SubtitleResult>> {
+        query_result(
+            client,
+            "/api/v1/vc/query",
+            "subtitle",
+            appid.as_ref(),
+            &self.id,
+        )
+        .await
+    }
+
+    pub fn events(
+        &self,
+        appid: impl Into<String>,
+        client: &Client,
+        policy: impl Into<PollPolicy>,
+    ) -> impl Stream<Item = JobEvent<SubtitleResult>> {
+        let (rep, appid, client) = (self.clone(), appid.into(), client.clone());
+        watch(policy.into().poller(&self.id), move || {
+            let (rep, appid, client) = (rep.clone(), appid.clone(), client.clone());
+            async move { rep.query(appid, &client).await }
+        })
+    }
+
     pub async fn waiting_result(
         &self,
         appid: impl AsRef<str>,
         client: &Client,
+        policy: impl Into<PollPolicy>,
     ) -> Result<SubtitleResult> {
-        let uri = "/api/v1/vc/query";
         trace!("waiting appid={}, id={}", appid.as_ref(), self.id);
-        let rep = client
-            .call(
-                Method::GET,
-                uri,
-                vec![
-                    ("appid".into(), appid.as_ref().to_string()),
-                    ("id".into(), self.id.to_owned()),
-                    ("blocking".into(), "1".into()),
-                ],
-                vec![],
-                None,
-            )
-            .await?;
-        let rep: Value = serde_json::from_slice(rep.bytes().await?.as_ref())?;
-        for l in serde_json::to_string_pretty(&rep)?.lines() {
-            trace!("REP: {}", l);
+        let mut poller = policy.into().poller(&self.id);
+        loop {
+            poller.next_attempt().await?;
+            match poller.attempt(self.query(appid.as_ref(), client)).await {
+                Ok(JobStatus::Done(rep)) => return Ok(rep),
+                Ok(_) => trace!(
+                    "subtitle job {} pending, attempt={}",
+                    self.id,
+                    poller.attempts()
+                ),
+                Err(e) if e.is_retryable() => warn!(
+                    "subtitle job {} query failed, retrying: {}, attempt={}",
+                    self.id,
+                    e,
+                    poller.attempts()
+                ),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+pub(crate) async fn query_result(
+    client: &Client,
+    path: &str,
+    kind: &str,
+    appid: &str,
+    id: &str,
+) -> Result<JobStatus<SubtitleResult>> {
+    let rep = client
+        .call(
+            Method::GET,
+            path,
+            vec![
+                ("appid".into(), appid.to_string()),
+                ("id".into(), id.to_owned()),
+                ("blocking".into(), "0".into()),
+            ],
+            vec![],
+            None,
+        )
+        .await?;
+    let raw = RawResponse::read(rep).await?.error_for_status()?;
+    let rep: Value = raw.json()?;
+    match rep.get("code").and_then(|v| v.as_i64()) {
+        Some(0) => Ok(JobStatus::Done(raw.parse(rep)?)),
+        Some(2000) => Ok(JobStatus::Processing),
+        _ => {
+            let e = raw.api_error();
+            if !e.is_retryable() {
+                error!("{} job {} failed: {}", kind, id, e);
+            }
+            Err(e)
         }
-        let rep: SubtitleResult = serde_json::from_value(rep)?;
-        Ok(rep)
     }
 }
 
@@ -238,6 +307,64 @@ pub struct Utterance {
     pub attribute: Attribute,
 }
 
+#[cfg(test)]
+#[tokio::test]
+async fn test_subtitle_events() -> Result<()> {
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let mock_client = |codes: &'static [i64]| {
+        let queries = AtomicUsize::new(0);
+        Client::builder()
+            .transport(crate::transport::json_mock(move |req| {
+                assert_eq!(req.url().path(), "/api/v1/vc/query");
+                let query = req.url().query().unwrap_or_default().to_string();
+                assert!(query.contains("appid=100") && query.contains("id=vc-job"));
+                let n = queries.fetch_add(1, Ordering::SeqCst);
+                match codes[n.min(codes.len() - 1)] {
+                    0 => json!({
+                        "code": 0,
+                        "message": "Success",
+                        "id": "vc-job",
+                        "duration": 1.0,
+                        "attribute": {},
+                        "utterances": [
+                            {"start_time": 0, "end_time": 800, "text": "你好", "attribute": {}, "words": []}
+                        ]
+                    }),
+                    code => json!({"code": code, "message": "", "id": "vc-job"}),
+                }
+            }))
+            .build()
+    };
+    let rep = SubtitleResponse {
+        code: 0,
+        message: "Success".into(),
+        id: "vc-job".into(),
+    };
+    let policy = PollPolicy::fixed(std::time::Duration::ZERO);
+
+    let client = mock_client(&[2000, 2000, 0])?;
+    let events = rep
+        .events("100", &client, policy.clone())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(events.len(), 4);
+    assert!(matches!(&events[0], JobEvent::Submitted { id } if id == "vc-job"));
+    assert!(matches!(events[1], JobEvent::Processing { attempt: 1 }));
+    assert!(matches!(events[2], JobEvent::Processing { attempt: 2 }));
+    assert!(matches!(&events[3], JobEvent::Succeeded(r) if r.utterances[0].text == "你好"));
+
+    let client = mock_client(&[2000, 1013])?;
+    let events = rep.events("100", &client, policy).collect::<Vec<_>>().await;
+    assert_eq!(events.len(), 3);
+    assert!(matches!(
+        &events[2],
+        JobEvent::Failed(e) if e.api().and_then(|e| e.code.as_deref()) == Some("1013")
+    ));
+    Ok(())
+}
+
 #[cfg(test)]
 #[tokio::test]
 async fn test_subtitle_ok() -> Result<()> {
@@ -257,7 +384,7 @@ async fn test_subtitle_ok() -> Result<()> {
         .build()?
         .call(&client)
         .await?
-        .waiting_result(appid, &client)
+        .waiting_result(appid, &client, PollPolicy::default())
         .await?;
 
     for l in serde_json::to_string_pretty(&rep)?.lines() {
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::error::*;
+use tracing::warn;
+
+pub const ENV_ACCESS_TOKEN: &str = "VOLCENGINE_ACCESS_TOKEN";
+pub const ENV_ACCESS_KEY_ID: &str = "VOLCENGINE_ACCESS_KEY_ID";
+pub const ENV_SECRET_ACCESS_KEY: &str = "VOLCENGINE_SECRET_ACCESS_KEY";
+pub const ENV_SESSION_TOKEN: &str = "VOLCENGINE_SESSION_TOKEN";
+pub const ENV_APP_ID: &str = "VOLCENGINE_APP_ID";
+pub const ENV_CLUSTER: &str = "VOLCENGINE_CLUSTER";
+pub const ENV_PROFILE: &str = "VOLCENGINE_PROFILE";
+pub const ENV_CONFIG_FILE: &str = "VOLCENGINE_CONFIG_FILE";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Credentials {
+    pub access_token: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    pub app_id: Option<String>,
+    pub cluster: Option<String>,
+}
+
+impl Credentials {
+    pub fn token(access_token: impl Into<String>) -> Self {
+        Self {
+            access_token: Some(access_token.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn access_key(
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            access_key_id: Some(access_key_id.into()),
+            secret_access_key: Some(secret_access_key.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(session_token.into());
+        self
+    }
+
+    pub fn with_app_id(mut self, app_id: impl Into<String>) -> Self {
+        self.app_id = Some(app_id.into());
+        self
+    }
+
+    pub fn with_cluster(mut self, cluster: impl Into<String>) -> Self {
+        self.cluster = Some(cluster.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.bearer_token().is_some()
+            || (self.access_key_id.is_some() && self.secret_access_key.is_some())
+    }
+
+    pub fn or(self, other: Credentials) -> Self {
+        Self {
+            access_token: self.access_token.or(other.access_token),
+            access_key_id: self.access_key_id.or(other.access_key_id),
+            secret_access_key: self.secret_access_key.or(other.secret_access_key),
+            session_token: self.session_token.or(other.session_token),
+            app_id: self.app_id.or(other.app_id),
+            cluster: self.cluster.or(other.cluster),
+        }
+    }
+
+    pub fn require_access_token(&self) -> Result<&str> {
+        self.bearer_token()
+            .ok_or(Error::MissingCredential("access_token"))
+    }
+
+    fn bearer_token(&self) -> Option<&str> {
+        self.access_token
+            .as_deref()
+            .filter(|token| !token.trim().is_empty())
+    }
+
+    pub fn require_access_key(&self) -> Result<(&str, &str)> {
+        Ok((
+            self.access_key_id
+                .as_deref()
+                .ok_or(Error::MissingCredential("access_key_id"))?,
+            self.secret_access_key
+                .as_deref()
+                .ok_or(Error::MissingCredential("secret_access_key"))?,
+        ))
+    }
+}
+
+pub trait CredentialsProvider: Send + Sync {
+    fn credentials(&self) -> Result<Credentials>;
+}
+
+impl CredentialsProvider for Credentials {
+    fn credentials(&self) -> Result<Credentials> {
+        Ok(self.clone())
+    }
+}
+
+impl<F> CredentialsProvider for F
+where
+    F: Fn() -> Result<Credentials> + Send + Sync,
+{
+    fn credentials(&self) -> Result<Credentials> {
+        self()
+    }
+}
+
+impl<T: CredentialsProvider + ?Sized> CredentialsProvider for Arc<T> {
+    fn credentials(&self) -> Result<Credentials> {
+        (**self).credentials()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EnvProvider;
+
+impl CredentialsProvider for EnvProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Ok(Credentials {
+            access_token: var(ENV_ACCESS_TOKEN),
+            access_key_id: var(ENV_ACCESS_KEY_ID),
+            secret_access_key: var(ENV_SECRET_ACCESS_KEY),
+            session_token: var(ENV_SESSION_TOKEN),
+            app_id: var(ENV_APP_ID),
+            cluster: var(ENV_CLUSTER),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileProvider {
+    pub path: Option<PathBuf>,
+    pub profile: Option<String>,
+    cache: Arc<Mutex<Option<(ProfileStamp, Credentials)>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ProfileStamp {
+    path: PathBuf,
+    profile: String,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl ProfileProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self.cache = Arc::default();
+        self
+    }
+
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self.cache = Arc::default();
+        self
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = std::env::var(ENV_CONFIG_FILE) {
+            return Some(path.into());
+        }
+        std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .ok()
+            .map(|home| PathBuf::from(home).join(".volcengine").join("config"))
+    }
+
+    pub fn parse(content: &str, profile: &str) -> Result<Credentials> {
+        let mut profiles: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut current = None;
+
+        for (no, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim();
+                let name = name.strip_prefix("profile ").unwrap_or(name).trim();
+                current = Some(name.to_string());
+                profiles.entry(name.to_string()).or_default();
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or(Error::Profile {
+                line: no + 1,
+                message: format!("expected `key = value`, found `{}`", line),
+            })?;
+            let section = current.as_ref().ok_or(Error::Profile {
+                line: no + 1,
+                message: "key outside of a [profile] section".into(),
+            })?;
+            profiles
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+
+        let Some(mut values) = profiles.remove(profile) else {
+            return Ok(Credentials::default());
+        };
+
+        let mut take = |key: &str| values.remove(key).filter(|v| !v.is_empty());
+
+        Ok(Credentials {
+            access_token: take("access_token"),
+            access_key_id: take("access_key_id"),
+            secret_access_key: take("secret_access_key"),
+            session_token: take("session_token"),
+            app_id: take("app_id"),
+            cluster: take("cluster"),
+        })
+    }
+}
+
+impl CredentialsProvider for ProfileProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        let Some(path) = self.path.clone().or_else(Self::default_path) else {
+            return Ok(Credentials::default());
+        };
+        let metadata = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                *self.cache.lock().expect("profile cache lock") = None;
+                return Ok(Credentials::default());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let stamp = ProfileStamp {
+            path,
+            profile: self
+                .profile
+                .clone()
+                .or_else(|| std::env::var(ENV_PROFILE).ok())
+                .unwrap_or_else(|| "default".into()),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        };
+
+        let mut cache = self.cache.lock().expect("profile cache lock");
+        if let Some((cached, credentials)) = cache.as_ref() {
+            if *cached == stamp {
+                return Ok(credentials.clone());
+            }
+        }
+        let credentials = match std::fs::read_to_string(&stamp.path) {
+            Ok(content) => Self::parse(&content, &stamp.profile)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Credentials::default(),
+            Err(e) => return Err(e.into()),
+        };
+        *cache = match credentials.is_empty() {
+            true => None,
+            false => Some((stamp, credentials.clone())),
+        };
+        Ok(credentials)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct CredentialsChain {
+    pub providers: Vec<Arc<dyn CredentialsProvider>>,
+}
+
+impl CredentialsChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn standard() -> Self {
+        Self::new()
+            .with(EnvProvider)
+            .with(ProfileProvider::default())
+    }
+
+    pub fn with(mut self, provider: impl CredentialsProvider + 'static) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+
+    pub fn first(mut self, provider: impl CredentialsProvider + 'static) -> Self {
+        self.providers.insert(0, Arc::new(provider));
+        self
+    }
+}
+
+impl CredentialsProvider for CredentialsChain {
+    fn credentials(&self) -> Result<Credentials> {
+        let mut found = None;
+        let mut settings = Credentials::default();
+        let mut failure = None;
+        for provider in self.providers.iter() {
+            let credentials = match provider.credentials() {
+                Ok(credentials) => credentials,
+                Err(e) => {
+                    warn!("credentials provider failed: {}", e);
+                    failure = Some(e);
+                    continue;
+                }
+            };
+            settings = settings.or(Credentials {
+                app_id: credentials.app_id.clone(),
+                cluster: credentials.cluster.clone(),
+                ..Credentials::default()
+            });
+            if found.is_none() && credentials.is_complete() {
+                found = Some(credentials);
+            }
+        }
+        match found {
+            Some(credentials) => Ok(credentials.or(settings)),
+            None => Err(failure.unwrap_or(Error::NoCredentials)),
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_credentials_chain() -> Result<()> {
+    let config = r#"
+# volcengine config
+[default]
+access_token = default-token
+app_id = 100
+
+[profile staging]
+access_key_id = AKLTSTAGING
+secret_access_key = secret
+cluster = volc_auc_common
+"#;
+
+    let default = ProfileProvider::parse(config, "default")?;
+    assert_eq!(default.access_token.as_deref(), Some("default-token"));
+    assert_eq!(default.app_id.as_deref(), Some("100"));
+
+    let staging = ProfileProvider::parse(config, "staging")?;
+    assert_eq!(staging.require_access_key()?, ("AKLTSTAGING", "secret"));
+    assert!(ProfileProvider::parse(config, "missing")?.is_empty());
+    assert!(matches!(
+        ProfileProvider::parse("[default]\nbroken", "default"),
+        Err(Error::Profile { line: 2, .. })
+    ));
+
+    let chain = CredentialsChain::new()
+        .with(Credentials::token("explicit-token"))
+        .with(default)
+        .with(|| Ok(Credentials::default().with_cluster("custom")));
+    let credentials = chain.credentials()?;
+    assert_eq!(credentials.require_access_token()?, "explicit-token");
+    assert_eq!(credentials.app_id.as_deref(), Some("100"));
+    assert_eq!(credentials.cluster.as_deref(), Some("custom"));
+
+    let chain = CredentialsChain::new()
+        .with(|| Err(Error::MissingCredential("broken")))
+        .with(Credentials::access_key("AKLTENV", "env-secret"))
+        .with(Credentials::access_key("AKLTPROFILE", "profile-secret").with_session_token("stale"));
+    let credentials = chain.credentials()?;
+    assert_eq!(credentials.require_access_key()?, ("AKLTENV", "env-secret"));
+    assert_eq!(credentials.session_token, None);
+
+    assert!(!Credentials::token(" ").is_complete());
+    assert!(matches!(
+        Credentials::token("").require_access_token(),
+        Err(Error::MissingCredential("access_token"))
+    ));
+    let chain = CredentialsChain::new()
+        .with(Credentials::token(""))
+        .with(Credentials::token("fallback"));
+    assert_eq!(chain.credentials()?.require_access_token()?, "fallback");
+
+    let chain = CredentialsChain::new()
+        .with(|| Ok(Credentials::default().with_session_token("orphan")))
+        .with(Credentials::token("complete"));
+    assert_eq!(chain.credentials()?.session_token, None);
+
+    assert!(matches!(
+        CredentialsChain::new().credentials(),
+        Err(Error::NoCredentials)
+    ));
+    assert!(matches!(
+        CredentialsChain::new()
+            .with(|| Err(Error::MissingCredential("broken")))
+            .credentials(),
+        Err(Error::MissingCredential("broken"))
+    ));
+
+    let path = std::env::temp_dir().join(format!("volcengine-profile-{}", std::process::id()));
+    std::fs::write(&path, config)?;
+    let provider = ProfileProvider::new().path(&path).profile("staging");
+    assert_eq!(provider.credentials()?, staging);
+    assert_eq!(provider.credentials()?, staging);
+    std::fs::write(
+        &path,
+        config
+            .replace("AKLTSTAGING", "AKLTROTATED")
+            .replace("= secret", "= rotated-secret"),
+    )?;
+    assert_eq!(
+        provider.credentials()?.require_access_key()?,
+        ("AKLTROTATED", "rotated-secret")
+    );
+    std::fs::remove_file(&path)?;
+    assert!(provider.credentials()?.is_empty());
+    std::fs::write(&path, config)?;
+    assert_eq!(provider.credentials()?, staging);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+pub mod credentials;
+pub mod signer;
+pub mod sts;
+
+use std::sync::Arc;
+
+use crate::error::*;
+use http::{header, HeaderValue};
+use reqwest::Request;
+
+pub use credentials::{Credentials, CredentialsChain, CredentialsProvider};
+pub use signer::Signer;
+pub use sts::StsProvider;
+
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, req: &mut Request) -> Result<()>;
+
+    fn access_token(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct BearerToken {
+    pub credentials: Arc<dyn CredentialsProvider>,
+}
+
+impl BearerToken {
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self::from_provider(Credentials::token(access_token))
+    }
+
+    pub fn from_provider(provider: impl CredentialsProvider + 'static) -> Self {
+        Self {
+            credentials: Arc::new(provider),
+        }
+    }
+}
+
+impl Default for BearerToken {
+    fn default() -> Self {
+        Self::from_provider(Credentials::default())
+    }
+}
+
+impl Authorizer for BearerToken {
+    fn authorize(&self, req: &mut Request) -> Result<()> {
+        let credentials = self.credentials.credentials()?;
+        req.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer; {}", credentials.require_access_token()?))?,
+        );
+        Ok(())
+    }
+
+    fn access_token(&self) -> Option<String> {
+        self.credentials
+            .credentials()
+            .ok()?
+            .require_access_token()
+            .ok()
+            .map(|token| token.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Anonymous;
+
+impl Authorizer for Anonymous {
+    fn authorize(&self, _req: &mut Request) -> Result<()> {
+        Ok(())
+    }
+}
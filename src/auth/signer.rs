@@ -0,0 +1,245 @@
+use std::sync::Arc;
+
+use crate::{
+    auth::{Authorizer, CredentialsProvider},
+    error::*,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http::{header, HeaderName, HeaderValue};
+use reqwest::Request;
+use sha2::{Digest, Sha256};
+
+pub const ALGORITHM: &str = "HMAC-SHA256";
+
+const X_DATE: HeaderName = HeaderName::from_static("x-date");
+const X_CONTENT_SHA256: HeaderName = HeaderName::from_static("x-content-sha256");
+const X_SECURITY_TOKEN: HeaderName = HeaderName::from_static("x-security-token");
+
+#[derive(Clone)]
+pub struct Signer {
+    pub credentials: Arc<dyn CredentialsProvider>,
+    pub region: String,
+    pub service: String,
+}
+
+impl Signer {
+    pub fn new(
+        credentials: impl CredentialsProvider + 'static,
+        region: impl Into<String>,
+        service: impl Into<String>,
+    ) -> Self {
+        Self {
+            credentials: Arc::new(credentials),
+            region: region.into(),
+            service: service.into(),
+        }
+    }
+
+    pub fn sign(&self, req: &mut Request, now: DateTime<Utc>) -> Result<()> {
+        let credentials = self.credentials.credentials()?;
+        let (access_key_id, secret_access_key) = credentials.require_access_key()?;
+
+        let x_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = &x_date[..8];
+
+        let payload = match req.body() {
+            Some(body) => body.as_bytes().ok_or(Error::UnsignableBody)?,
+            None => &[],
+        };
+        let payload_hash = hex::encode(Sha256::digest(payload));
+
+        let headers = req.headers_mut();
+        headers.insert(X_DATE, HeaderValue::from_str(&x_date)?);
+        headers.insert(X_CONTENT_SHA256, HeaderValue::from_str(&payload_hash)?);
+        if let Some(token) = &credentials.session_token {
+            headers.insert(X_SECURITY_TOKEN, HeaderValue::from_str(token)?);
+        }
+
+        let mut signed = vec![("host".to_string(), host(req.url()))];
+        for (name, value) in req.headers() {
+            let name = name.as_str();
+            if name == "content-type" || name.starts_with("x-") {
+                signed.push((
+                    name.to_string(),
+                    value.to_str().unwrap_or_default().trim().into(),
+                ));
+            }
+        }
+
+        let (signed_headers, canonical_request) =
+            canonical_request(req.method(), req.url(), signed, &payload_hash);
+
+        let scope = format!("{}/{}/{}/request", date, self.region, self.service);
+
+        let string_to_sign = [
+            ALGORITHM,
+            &x_date,
+            &scope,
+            &hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        ]
+        .join("\n");
+
+        let key = signing_key(secret_access_key, date, &self.region, &self.service);
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, access_key_id, scope, signed_headers, signature
+        );
+
+        req.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&authorization)?,
+        );
+
+        Ok(())
+    }
+}
+
+impl Authorizer for Signer {
+    fn authorize(&self, req: &mut Request) -> Result<()> {
+        self.sign(req, Utc::now())
+    }
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub(crate) fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(secret.as_bytes(), date.as_bytes());
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"request")
+}
+
+pub(crate) fn canonical_request(
+    method: &http::Method,
+    url: &url::Url,
+    mut signed: Vec<(String, String)>,
+    payload_hash: &str,
+) -> (String, String) {
+    signed.sort();
+
+    let signed_headers = signed
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_headers = signed
+        .iter()
+        .map(|(k, v)| format!("{}:{}\n", k, v))
+        .collect::<String>();
+
+    let canonical_request = [
+        method.as_str(),
+        canonical_path(url),
+        &canonical_query(url),
+        &canonical_headers,
+        &signed_headers,
+        payload_hash,
+    ]
+    .join("\n");
+
+    (signed_headers, canonical_request)
+}
+
+pub(crate) fn host(url: &url::Url) -> String {
+    match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    }
+}
+
+pub(crate) fn canonical_path(url: &url::Url) -> &str {
+    match url.path() {
+        "" => "/",
+        path => path,
+    }
+}
+
+pub(crate) fn canonical_query(url: &url::Url) -> String {
+    let mut pairs = url
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect::<Vec<_>>();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub(crate) fn uri_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+#[test]
+fn test_signer_vector() -> Result<()> {
+    use crate::auth::Credentials;
+    use chrono::TimeZone;
+    use http::Method;
+
+    let client = reqwest::Client::new();
+    let mut req = client
+        .request(
+            Method::POST,
+            "https://open.volcengineapi.com/?Action=ListUsers&Version=2018-01-01&Limit=10",
+        )
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(r#"{"Offset":0}"#)
+        .build()?;
+
+    let credentials = Credentials::access_key("AKLTEXAMPLE", "c2VjcmV0LWtleS1leGFtcGxl");
+    let signer = Signer::new(
+        credentials.clone().with_session_token("session-token"),
+        "cn-north-1",
+        "iam",
+    );
+    let now = Utc.with_ymd_and_hms(2024, 8, 28, 12, 30, 45).unwrap();
+    signer.sign(&mut req, now)?;
+
+    let headers = req.headers();
+    assert_eq!(headers["x-date"], "20240828T123045Z");
+    assert_eq!(
+        headers["x-content-sha256"],
+        "17b6c2ff5c3cb75a8f4e16bd65171d68c24b9fb5303a0bfc688a0cc70fad3949"
+    );
+    assert_eq!(headers["x-security-token"], "session-token");
+    assert_eq!(
+        headers[header::AUTHORIZATION],
+        "HMAC-SHA256 Credential=AKLTEXAMPLE/20240828/cn-north-1/iam/request, \
+         SignedHeaders=content-type;host;x-content-sha256;x-date;x-security-token, \
+         Signature=02969efb062d9f6997c12392cc77eeb2e130fdb8e893bcaff60f787374f17b49"
+    );
+
+    let mut req = client
+        .request(
+            Method::GET,
+            "https://open.volcengineapi.com/?Action=GetUser&UserName=a b",
+        )
+        .build()?;
+    Signer::new(credentials, "cn-north-1", "iam").sign(&mut req, now)?;
+    assert_eq!(
+        req.headers()[header::AUTHORIZATION],
+        "HMAC-SHA256 Credential=AKLTEXAMPLE/20240828/cn-north-1/iam/request, \
+         SignedHeaders=host;x-content-sha256;x-date, \
+         Signature=ef334c701d0dc3ce3de7d1560163ca051638ff5be9aa15ee3caf848459f11edf"
+    );
+    Ok(())
+}
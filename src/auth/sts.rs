@@ -0,0 +1,426 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::{
+    auth::{Credentials, CredentialsProvider},
+    client::Client,
+    error::*,
+    openapi,
+    poll::Backoff,
+};
+use chrono::{DateTime, FixedOffset, Utc};
+use http::Method;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{error, info};
+
+pub const STS_SERVICE: &str = "sts";
+pub const STS_VERSION: &str = "2018-01-01";
+pub const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+pub const MAX_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssumeRoleRequest {
+    pub role_trn: String,
+    pub role_session_name: String,
+    pub duration_seconds: Option<u32>,
+    pub policy: Option<String>,
+}
+
+impl AssumeRoleRequest {
+    pub fn new(role_trn: impl Into<String>, role_session_name: impl Into<String>) -> Self {
+        Self {
+            role_trn: role_trn.into(),
+            role_session_name: role_session_name.into(),
+            duration_seconds: None,
+            policy: None,
+        }
+    }
+
+    pub fn duration_seconds(mut self, duration_seconds: u32) -> Self {
+        self.duration_seconds = Some(duration_seconds);
+        self
+    }
+
+    pub fn policy(mut self, policy: impl Into<String>) -> Self {
+        self.policy = Some(policy.into());
+        self
+    }
+
+    pub async fn call(&self, client: &Client) -> Result<AssumeRoleResult> {
+        let mut queries = vec![
+            ("RoleTrn".to_string(), self.role_trn.clone()),
+            (
+                "RoleSessionName".to_string(),
+                self.role_session_name.clone(),
+            ),
+        ];
+        if let Some(duration) = self.duration_seconds {
+            queries.push(("DurationSeconds".into(), duration.to_string()));
+        }
+        if let Some(policy) = &self.policy {
+            queries.push(("Policy".into(), policy.clone()));
+        }
+        openapi::call(
+            client,
+            Method::GET,
+            "AssumeRole",
+            STS_VERSION,
+            queries,
+            vec![],
+            None,
+        )
+        .await
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssumeRoleResult {
+    pub credentials: StsCredentials,
+    pub assumed_role_user: Option<AssumedRoleUser>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssumedRoleUser {
+    pub trn: String,
+    pub assumed_role_id: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct StsCredentials {
+    pub current_time: Option<DateTime<FixedOffset>>,
+    pub expired_time: DateTime<FixedOffset>,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+}
+
+impl StsCredentials {
+    pub fn is_expired(&self) -> bool {
+        self.expired_time <= Utc::now()
+    }
+
+    pub fn expires_in(&self) -> Duration {
+        (self.expired_time.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default()
+    }
+
+    pub fn lifetime(&self) -> Duration {
+        match self.current_time {
+            Some(current_time) => (self.expired_time - current_time)
+                .to_std()
+                .unwrap_or_default(),
+            None => self.expires_in(),
+        }
+    }
+}
+
+impl From<StsCredentials> for Credentials {
+    fn from(value: StsCredentials) -> Self {
+        Credentials::access_key(value.access_key_id, value.secret_access_key)
+            .with_session_token(value.session_token)
+    }
+}
+
+#[derive(Clone)]
+pub struct StsProvider {
+    current: Arc<RwLock<Current>>,
+    refresher: Arc<Refresher>,
+}
+
+struct Current {
+    credentials: StsCredentials,
+    expires_at: Instant,
+}
+
+impl Current {
+    fn new(credentials: StsCredentials) -> Self {
+        Self {
+            expires_at: Instant::now() + credentials.lifetime(),
+            credentials,
+        }
+    }
+}
+
+struct Refresher(JoinHandle<()>);
+
+impl Drop for Refresher {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl StsProvider {
+    pub async fn assume_role(client: Client, request: AssumeRoleRequest) -> Result<Self> {
+        Self::assume_role_with(client, request, Duration::from_secs(300)).await
+    }
+
+    pub async fn assume_role_with(
+        client: Client,
+        request: AssumeRoleRequest,
+        refresh_before: Duration,
+    ) -> Result<Self> {
+        let credentials = request.call(&client).await?.credentials;
+        let lifetime = credentials.lifetime();
+        if refresh_before >= lifetime {
+            return Err(Error::StsRefreshWindow {
+                refresh_before,
+                lifetime,
+            });
+        }
+        info!(
+            "assumed role {}, expires at {}",
+            request.role_trn, credentials.expired_time
+        );
+        let current = Arc::new(RwLock::new(Current::new(credentials)));
+        let task = tokio::spawn(refresh(client, request, current.clone(), refresh_before));
+        Ok(Self {
+            current,
+            refresher: Arc::new(Refresher(task)),
+        })
+    }
+
+    pub fn current(&self) -> StsCredentials {
+        self.current
+            .read()
+            .expect("sts credentials lock")
+            .credentials
+            .clone()
+    }
+
+    pub fn expires_in(&self) -> Duration {
+        self.current
+            .read()
+            .expect("sts credentials lock")
+            .expires_at
+            .saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_refreshing(&self) -> bool {
+        !self.refresher.0.is_finished()
+    }
+}
+
+impl CredentialsProvider for StsProvider {
+    fn credentials(&self) -> Result<Credentials> {
+        let current = self.current();
+        match self.expires_in().is_zero() {
+            true => Err(Error::CredentialsExpired(current.expired_time.to_rfc3339())),
+            false => Ok(current.into()),
+        }
+    }
+}
+
+async fn refresh(
+    client: Client,
+    request: AssumeRoleRequest,
+    current: Arc<RwLock<Current>>,
+    refresh_before: Duration,
+) {
+    loop {
+        let expires_at = current.read().expect("sts credentials lock").expires_at;
+        tokio::time::sleep(refresh_delay(
+            expires_at.saturating_duration_since(Instant::now()),
+            refresh_before,
+        ))
+        .await;
+
+        let mut backoff = Backoff::new(MIN_REFRESH_INTERVAL, MAX_REFRESH_INTERVAL, 2.0, 0.2);
+        let mut reported = false;
+        loop {
+            match request.call(&client).await {
+                Ok(result) => {
+                    info!(
+                        "refreshed role {}, expires at {}",
+                        request.role_trn, result.credentials.expired_time
+                    );
+                    *current.write().expect("sts credentials lock") =
+                        Current::new(result.credentials);
+                    break;
+                }
+                Err(e) if e.is_retryable() => {
+                    let delay = backoff.next_delay();
+                    error!(
+                        "failed to refresh role {}: {}, retry in {:?}",
+                        request.role_trn, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    if !reported {
+                        error!(
+                            "failed to refresh role {}: {}, retry every {:?}",
+                            request.role_trn, e, MAX_REFRESH_INTERVAL
+                        );
+                        reported = true;
+                    }
+                    tokio::time::sleep(MAX_REFRESH_INTERVAL).await;
+                }
+            }
+        }
+    }
+}
+
+fn refresh_delay(remaining: Duration, refresh_before: Duration) -> Duration {
+    let delay = match remaining > refresh_before {
+        true => remaining - refresh_before,
+        false => remaining / 2,
+    };
+    delay.max(MIN_REFRESH_INTERVAL)
+}
+
+#[cfg(test)]
+#[test]
+fn test_assume_role_result() -> Result<()> {
+    let rep = r#"{
+        "ResponseMetadata": {
+            "RequestId": "20210412193736010225143191064C8F4B",
+            "Action": "AssumeRole",
+            "Version": "2018-01-01",
+            "Service": "sts",
+            "Region": "cn-north-1"
+        },
+        "Result": {
+            "Credentials": {
+                "CurrentTime": "2021-04-12T19:37:36+08:00",
+                "ExpiredTime": "2021-04-12T20:37:36+08:00",
+                "AccessKeyId": "AKTPYzdkMjFjNTc3N2M0NDhiMTc0",
+                "SecretAccessKey": "TlRaaE1XTXhNV0ZtTXpRMk5ETXlZVGhsWWpSbFpUQTFNbVUzTVRNeU9EVQ==",
+                "SessionToken": "STSeyJBY2NvdW50SWQiOjIxMDAwMDAwMDF9"
+            },
+            "AssumedRoleUser": {
+                "Trn": "trn:sts::2100000001:assumed-role/test/session",
+                "AssumedRoleId": "1:test:session"
+            }
+        }
+    }"#;
+
+    let result = serde_json::from_str::<openapi::OpenApiResponse<AssumeRoleResult>>(rep)?
+        .result
+        .unwrap();
+    assert!(result.credentials.is_expired());
+    assert_eq!(result.credentials.expires_in(), Duration::ZERO);
+
+    let credentials = Credentials::from(result.credentials);
+    assert_eq!(
+        credentials.require_access_key()?,
+        (
+            "AKTPYzdkMjFjNTc3N2M0NDhiMTc0",
+            "TlRaaE1XTXhNV0ZtTXpRMk5ETXlZVGhsWWpSbFpUQTFNbVUzTVRNeU9EVQ=="
+        )
+    );
+    assert_eq!(
+        credentials.session_token.as_deref(),
+        Some("STSeyJBY2NvdW50SWQiOjIxMDAwMDAwMDF9")
+    );
+
+    let rep = r#"{
+        "ResponseMetadata": {
+            "RequestId": "20210412193736010225143191064C8F4B",
+            "Action": "AssumeRole",
+            "Version": "2018-01-01",
+            "Error": {"Code": "InvalidParameter", "Message": "RoleTrn is invalid"}
+        }
+    }"#;
+    let e = ApiError::new(
+        http::StatusCode::BAD_REQUEST,
+        &Default::default(),
+        rep.as_bytes(),
+    );
+    assert_eq!(e.code.as_deref(), Some("InvalidParameter"));
+    assert_eq!(e.message, "RoleTrn is invalid");
+    assert_eq!(
+        e.log_id.as_deref(),
+        Some("20210412193736010225143191064C8F4B")
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test(start_paused = true)]
+async fn test_sts_background_refresh() -> Result<()> {
+    use crate::transport::MockResponse;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let client = Client::builder()
+        .transport(crate::transport::json_mock({
+            let calls = calls.clone();
+            move |req| {
+                assert!(req.url().query().unwrap().contains("Action=AssumeRole"));
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                if n >= 3 {
+                    return MockResponse::json(serde_json::json!({
+                        "ResponseMetadata": {
+                            "RequestId": "req",
+                            "Action": "AssumeRole",
+                            "Error": {"Code": "AccessDenied", "Message": "role deleted"}
+                        }
+                    }))
+                    .status(403);
+                }
+                let now = Utc::now();
+                MockResponse::json(serde_json::json!({
+                    "ResponseMetadata": {"RequestId": "req", "Action": "AssumeRole"},
+                    "Result": {"Credentials": {
+                        "CurrentTime": now.to_rfc3339(),
+                        "ExpiredTime": (now + chrono::Duration::seconds(3)).to_rfc3339(),
+                        "AccessKeyId": format!("AKTP{}", n),
+                        "SecretAccessKey": "secret",
+                        "SessionToken": format!("session-{}", n),
+                    }}
+                }))
+            }
+        }))
+        .build()?;
+    let request = AssumeRoleRequest::new("trn:iam::2100000001:role/test", "session");
+
+    assert!(matches!(
+        StsProvider::assume_role_with(client.clone(), request.clone(), Duration::from_secs(5))
+            .await,
+        Err(Error::StsRefreshWindow { .. })
+    ));
+
+    let provider =
+        StsProvider::assume_role_with(client, request, Duration::from_millis(1500)).await?;
+    let first = provider.credentials()?;
+    assert_eq!(first.access_key_id.as_deref(), Some("AKTP1"));
+    assert_eq!(provider.expires_in(), Duration::from_secs(3));
+
+    let started = Instant::now();
+    while provider.current().access_key_id == "AKTP1" {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(1500), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    assert!(provider.expires_in() > Duration::from_secs(2));
+    let refreshed = provider.credentials()?;
+    assert_eq!(refreshed.access_key_id.as_deref(), Some("AKTP2"));
+    assert_eq!(refreshed.session_token.as_deref(), Some("session-2"));
+    assert!(provider.is_refreshing());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+    tokio::time::sleep(MAX_REFRESH_INTERVAL).await;
+    assert_eq!(calls.load(Ordering::SeqCst), 5);
+    assert!(provider.credentials().is_err());
+    Ok(())
+}
+
+#[cfg(test)]
+#[test]
+fn test_sts_refresh_delay() {
+    let minute = Duration::from_secs(60);
+    assert_eq!(refresh_delay(60 * minute, 5 * minute), 55 * minute);
+    assert_eq!(refresh_delay(4 * minute, 5 * minute), 2 * minute);
+    assert_eq!(
+        refresh_delay(Duration::ZERO, 5 * minute),
+        MIN_REFRESH_INTERVAL
+    );
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use volcengine::{
+    error::*,
+    mock::{MockConfig, MockServer},
+};
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: volcengine-mock [--addr 127.0.0.1:8080] [--queued N] [--processing N] \
+         [--latency-ms N] [--unavailable N] [--fail CODE:MESSAGE] [--token TOKEN] \
+         [--transcript TEXT]"
+    );
+    std::process::exit(2)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut config = MockConfig::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        let number = || value.parse::<u32>().unwrap_or_else(|_| usage());
+        config = match arg.as_str() {
+            "--addr" => {
+                addr = value.clone();
+                config
+            }
+            "--queued" => config.queued_polls(number()),
+            "--processing" => config.processing_polls(number()),
+            "--latency-ms" => config.latency(Duration::from_millis(number() as u64)),
+            "--unavailable" => config.unavailable(number()),
+            "--fail" => {
+                let (code, message) = value.split_once(':').unwrap_or((&value, "mock failure"));
+                config.failure(code.parse().unwrap_or_else(|_| usage()), message)
+            }
+            "--token" => config.access_token(value.clone()),
+            "--transcript" => config.transcript(value.clone()),
+            _ => usage(),
+        };
+    }
+
+    let server = MockServer::bind(&addr, config).await?;
+    println!("{}", server.url());
+    server.wait().await;
+    Ok(())
+}
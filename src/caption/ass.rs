@@ -0,0 +1,335 @@
+use std::fmt::Write;
+
+use crate::{
+    asr::subtitle::Utterance,
+    caption::{lines, parse_clock, parse_error, source_lines, span, utterance, Cue},
+    error::*,
+};
+use smart_default::SmartDefault;
+
+#[derive(Debug, Clone, SmartDefault, PartialEq)]
+pub struct AssStyle {
+    #[default("Default".to_string())]
+    pub name: String,
+    #[default("Arial".to_string())]
+    pub font_name: String,
+    #[default(48)]
+    pub font_size: u32,
+    #[default("&H00FFFFFF".to_string())]
+    pub primary_colour: String,
+    #[default("&H000000FF".to_string())]
+    pub secondary_colour: String,
+    #[default("&H00000000".to_string())]
+    pub outline_colour: String,
+    #[default("&H64000000".to_string())]
+    pub back_colour: String,
+    pub bold: bool,
+    pub italic: bool,
+    #[default(1)]
+    pub border_style: u8,
+    #[default(2.0)]
+    pub outline: f32,
+    #[default(1.0)]
+    pub shadow: f32,
+    #[default(2)]
+    pub alignment: u8,
+    #[default(20)]
+    pub margin_l: u32,
+    #[default(20)]
+    pub margin_r: u32,
+    #[default(40)]
+    pub margin_v: u32,
+    #[default(1920)]
+    pub play_res_x: u32,
+    #[default(1080)]
+    pub play_res_y: u32,
+}
+
+impl AssStyle {
+    fn field_name(&self) -> String {
+        self.name.replace(',', " ")
+    }
+
+    pub fn header(&self) -> String {
+        let flag = |on: bool| if on { -1 } else { 0 };
+        format!(
+            "[Script Info]\n\
+             ScriptType: v4.00+\n\
+             PlayResX: {}\n\
+             PlayResY: {}\n\
+             WrapStyle: 0\n\
+             ScaledBorderAndShadow: yes\n\
+             \n\
+             [V4+ Styles]\n\
+             Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, \
+             BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, \
+             BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+             Style: {},{},{},{},{},{},{},{},{},0,0,100,100,0,0,{},{},{},{},{},{},{},1\n\
+             \n\
+             [Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+            self.play_res_x,
+            self.play_res_y,
+            self.field_name(),
+            self.font_name,
+            self.font_size,
+            self.primary_colour,
+            self.secondary_colour,
+            self.outline_colour,
+            self.back_colour,
+            flag(self.bold),
+            flag(self.italic),
+            self.border_style,
+            self.outline,
+            self.shadow,
+            self.alignment,
+            self.margin_l,
+            self.margin_r,
+            self.margin_v,
+        )
+    }
+}
+
+pub fn timestamp(ms: i64) -> String {
+    let cs = (ms.max(0) + 5) / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    parse_clock(s, '.', 2, false)
+}
+
+/// Escapes `\\`, `{` and `}` so cue text is never read as an override tag or
+/// an ASS escape such as `\N`.
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+}
+
+/// Drops `{...}` override blocks and resolves the escapes written by [`escape`]
+/// along with the `\N`, `\n` and `\h` line break and space tags.
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if depth == 0 => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            '\\' => match chars.next() {
+                Some('N' | 'n') => out.push('\n'),
+                Some('h') => out.push(' '),
+                Some(c @ ('{' | '}' | '\\')) => out.push(c),
+                Some(c) => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub const DEFAULT_EVENT_FORMAT: [&str; 10] = [
+    "Layer", "Start", "End", "Style", "Name", "MarginL", "MarginR", "MarginV", "Effect", "Text",
+];
+
+pub fn to_ass<'a, C: Cue + 'a>(cues: impl IntoIterator<Item = &'a C>, style: &AssStyle) -> String {
+    let mut out = style.header();
+    for cue in cues {
+        let text = lines(cue.text())
+            .map(escape)
+            .collect::<Vec<_>>()
+            .join("\\N");
+        if text.is_empty() {
+            continue;
+        }
+        let (start, end) = span(cue);
+        let _ = writeln!(
+            out,
+            "Dialogue: 0,{},{},{},{},0,0,0,,{}",
+            timestamp(start),
+            timestamp(end),
+            style.field_name(),
+            cue.speaker().unwrap_or_default().replace(',', " "),
+            text
+        );
+    }
+    out
+}
+
+pub fn parse(input: &str) -> Result<Vec<Utterance>> {
+    let mut utterances = vec![];
+    let mut events = false;
+    let mut format = DEFAULT_EVENT_FORMAT.map(String::from).to_vec();
+    for (line_no, line) in source_lines(input) {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            events = trimmed.eq_ignore_ascii_case("[events]");
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':').filter(|_| events) else {
+            continue;
+        };
+        match key.trim() {
+            "Format" => {
+                format = value.split(',').map(|f| f.trim().to_string()).collect();
+                if format.last().map(String::as_str) != Some("Text")
+                    || !format.iter().any(|f| f == "Start")
+                    || !format.iter().any(|f| f == "End")
+                {
+                    return Err(parse_error(
+                        line_no,
+                        line,
+                        key.len() + 1,
+                        "event format must include Start and End and end with Text",
+                    ));
+                }
+            }
+            "Dialogue" => {
+                let mut fields = vec![];
+                let mut offset = key.len() + 1;
+                for field in value.splitn(format.len(), ',') {
+                    fields.push((
+                        offset + field.len() - field.trim_start().len(),
+                        field.trim(),
+                    ));
+                    offset += field.len() + 1;
+                }
+                if fields.len() < format.len() {
+                    return Err(parse_error(
+                        line_no,
+                        line,
+                        line.len(),
+                        format!("expected {} fields, found {}", format.len(), fields.len()),
+                    ));
+                }
+                let field = |name: &str| {
+                    format
+                        .iter()
+                        .position(|f| f == name)
+                        .map(|i| fields[i])
+                        .unwrap_or((0, ""))
+                };
+                let time = |name: &str| {
+                    let (offset, value) = field(name);
+                    parse_timestamp(value).ok_or_else(|| {
+                        parse_error(
+                            line_no,
+                            line,
+                            offset,
+                            format!("invalid {} time {:?}", name.to_lowercase(), value),
+                        )
+                    })
+                };
+                let (start, end) = (time("Start")?, time("End")?);
+                if end < start {
+                    return Err(parse_error(
+                        line_no,
+                        line,
+                        field("End").0,
+                        "end time is before start time",
+                    ));
+                }
+                let text = unescape(field("Text").1);
+                let name = field("Name").1;
+                utterances.push(utterance(
+                    start,
+                    end,
+                    lines(&text).collect::<Vec<_>>().join("\n"),
+                    (!name.is_empty()).then(|| name.to_string()),
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(utterances)
+}
+
+#[cfg(test)]
+#[test]
+fn test_ass_writer() {
+    use crate::asr::record::Utterance;
+
+    assert_eq!(timestamp(3_723_004), "1:02:03.00");
+    assert_eq!(timestamp(1_235), "0:00:01.24");
+    assert_eq!(timestamp(59_996), "0:01:00.00");
+
+    let style = AssStyle::default();
+    assert!(style.header().contains(
+        "Style: Default,Arial,48,&H00FFFFFF,&H000000FF,&H00000000,&H64000000,0,0,0,0,\
+         100,100,0,0,1,2,1,2,20,20,40,1\n"
+    ));
+
+    let ass = to_ass(
+        &[Utterance {
+            start_time: 500,
+            end_time: 2_004,
+            text: "第一行\n第二行".into(),
+            words: vec![],
+            additions: None,
+        }],
+        &style,
+    );
+    assert!(ass.starts_with("[Script Info]\n"));
+    assert!(ass.ends_with(
+        "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+         Dialogue: 0,0:00:00.50,0:00:02.00,Default,,0,0,0,,第一行\\N第二行\n"
+    ));
+}
+
+#[cfg(test)]
+#[test]
+fn test_ass_parser() -> Result<()> {
+    let input = "[Script Info]\nTitle: Dialogue: not an event\n\n[Events]\n\
+                 Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                 Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,skipped\n\
+                 Dialogue: 0,0:00:00.50,0:00:02.00,Default,Bob,0,0,0,,{\\i1}第一行{\\i0}\\N第二行, 好\n";
+    let utterances = parse(input)?;
+    assert_eq!(utterances.len(), 1);
+    assert_eq!(
+        (utterances[0].start_time, utterances[0].end_time),
+        (500, 2000)
+    );
+    assert_eq!(utterances[0].text, "第一行\n第二行, 好");
+    assert_eq!(utterances[0].attribute.speaker.as_deref(), Some("Bob"));
+
+    let output = to_ass(&utterances, &AssStyle::default());
+    assert_eq!(output, to_ass(&parse(&output)?, &AssStyle::default()));
+
+    let literal = utterance(0, 1000, r"{\b1} C:\Nope \h}{".into(), None);
+    let style = AssStyle {
+        name: "Main, Top".into(),
+        ..Default::default()
+    };
+    let output = to_ass(&[literal], &style);
+    assert!(output.contains("Style: Main  Top,Arial,"));
+    assert!(output
+        .trim_end()
+        .ends_with(r",Main  Top,,0,0,0,,\{\\b1\} C:\\Nope \\h\}\{"));
+    let parsed = parse(&output)?;
+    assert_eq!(parsed[0].text, r"{\b1} C:\Nope \h}{");
+    assert_eq!(to_ass(&parsed, &style), output);
+
+    let position = |input: &str| match parse(input) {
+        Err(Error::CaptionParse { line, column, .. }) => (line, column),
+        r => panic!("unexpected {:?}", r.map(|u| u.len())),
+    };
+    assert_eq!(
+        position("[Events]\nDialogue: 0,0:00:01.00,0:00:0x.00,Default,,0,0,0,,a\n"),
+        (2, 24)
+    );
+    assert_eq!(position("[Events]\nDialogue: 0,0:00:01.00\n"), (2, 23));
+    assert_eq!(position("[Events]\nFormat: Start, Text, End\n"), (2, 8));
+    Ok(())
+}
@@ -0,0 +1,333 @@
+pub mod ass;
+pub mod segment;
+pub mod srt;
+pub mod vtt;
+
+pub use ass::AssStyle;
+pub use segment::Segmenter;
+pub use vtt::VttSettings;
+
+use crate::{
+    asr::{bigmodel, record, subtitle},
+    error::*,
+};
+
+pub trait Cue {
+    fn start_time(&self) -> i64;
+    fn end_time(&self) -> i64;
+    fn text(&self) -> &str;
+
+    fn speaker(&self) -> Option<&str> {
+        None
+    }
+
+    fn words(&self) -> Vec<&dyn Cue> {
+        vec![]
+    }
+}
+
+impl Cue for subtitle::Utterance {
+    fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    fn end_time(&self) -> i64 {
+        self.end_time
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn speaker(&self) -> Option<&str> {
+        self.attribute.speaker.as_deref()
+    }
+
+    fn words(&self) -> Vec<&dyn Cue> {
+        self.words.iter().map(|w| w as &dyn Cue).collect()
+    }
+}
+
+impl Cue for record::Utterance {
+    fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    fn end_time(&self) -> i64 {
+        self.end_time
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn speaker(&self) -> Option<&str> {
+        self.additions.as_ref()?.speaker.as_deref()
+    }
+
+    fn words(&self) -> Vec<&dyn Cue> {
+        self.words.iter().map(|w| w as &dyn Cue).collect()
+    }
+}
+
+impl Cue for subtitle::Word {
+    fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    fn end_time(&self) -> i64 {
+        self.end_time
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Cue for record::Word {
+    fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    fn end_time(&self) -> i64 {
+        self.end_time
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+pub trait Captions {
+    type Cue: Cue;
+
+    fn cues(&self) -> &[Self::Cue];
+
+    fn to_srt(&self) -> String {
+        srt::to_srt(self.cues())
+    }
+
+    fn to_vtt(&self, settings: &VttSettings) -> String {
+        vtt::to_vtt(self.cues(), settings)
+    }
+
+    fn to_ass(&self, style: &AssStyle) -> String {
+        ass::to_ass(self.cues(), style)
+    }
+
+    fn resegment(&self, segmenter: &Segmenter) -> Vec<subtitle::Utterance> {
+        segmenter.segment_utterances(self.cues())
+    }
+}
+
+impl Captions for subtitle::SubtitleResult {
+    type Cue = subtitle::Utterance;
+
+    fn cues(&self) -> &[Self::Cue] {
+        &self.utterances
+    }
+}
+
+impl Captions for record::RecordAsrResult {
+    type Cue = record::Utterance;
+
+    fn cues(&self) -> &[Self::Cue] {
+        &self.utterances
+    }
+}
+
+impl Captions for bigmodel::BigModelResult {
+    type Cue = record::Utterance;
+
+    fn cues(&self) -> &[Self::Cue] {
+        &self.result.utterances
+    }
+}
+
+pub(crate) fn span(cue: &(impl Cue + ?Sized)) -> (i64, i64) {
+    let start = cue.start_time().max(0);
+    (start, cue.end_time().max(start))
+}
+
+pub(crate) fn clock(ms: i64) -> (i64, i64, i64, i64) {
+    (ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+pub(crate) fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().map(str::trim).filter(|line| !line.is_empty())
+}
+
+pub(crate) fn source_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .strip_prefix('\u{feff}')
+        .unwrap_or(input)
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+}
+
+pub(crate) fn strip_markup(text: &str, open: char, close: char) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            c if c == open => depth += 1,
+            c if c == close && depth > 0 => depth -= 1,
+            c if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    out
+}
+
+pub(crate) fn parse_error(
+    line: usize,
+    text: &str,
+    offset: usize,
+    message: impl Into<String>,
+) -> Error {
+    Error::CaptionParse {
+        line,
+        column: text[..offset].chars().count() + 1,
+        message: message.into(),
+    }
+}
+
+pub(crate) fn parse_clock(s: &str, sep: char, digits: u32, optional_hours: bool) -> Option<i64> {
+    let number = |v: &str| {
+        (!v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| v.parse::<i64>().ok())
+            .flatten()
+    };
+    let (clock, frac) = s.split_once(sep)?;
+    if frac.len() != digits as usize {
+        return None;
+    }
+    let (h, m, s) = match clock.split(':').collect::<Vec<_>>()[..] {
+        [h, m, s] => (number(h)?, number(m)?, number(s)?),
+        [m, s] if optional_hours => (0, number(m)?, number(s)?),
+        _ => return None,
+    };
+    if m >= 60 || s >= 60 {
+        return None;
+    }
+    h.checked_mul(3_600_000)?
+        .checked_add(m * 60_000 + s * 1000)?
+        .checked_add(number(frac)? * 10i64.pow(3 - digits))
+}
+
+pub(crate) fn parse_timing(
+    line_no: usize,
+    line: &str,
+    parse: impl Fn(&str) -> Option<i64>,
+) -> Result<(i64, i64)> {
+    let arrow = line
+        .find("-->")
+        .ok_or_else(|| parse_error(line_no, line, 0, "expected timing \"start --> end\""))?;
+    let start_offset = line.len() - line.trim_start().len();
+    let start = line[..arrow].trim();
+    let start = parse(start).ok_or_else(|| {
+        parse_error(
+            line_no,
+            line,
+            start_offset,
+            format!("invalid start time {:?}", start),
+        )
+    })?;
+
+    let rest = &line[arrow + 3..];
+    let end_offset = arrow + 3 + rest.len() - rest.trim_start().len();
+    let end = rest.split_whitespace().next().unwrap_or_default();
+    let end = parse(end).ok_or_else(|| {
+        parse_error(
+            line_no,
+            line,
+            end_offset,
+            format!("invalid end time {:?}", end),
+        )
+    })?;
+    if end < start {
+        return Err(parse_error(
+            line_no,
+            line,
+            end_offset,
+            "end time is before start time",
+        ));
+    }
+    Ok((start, end))
+}
+
+pub(crate) fn utterance(
+    start_time: i64,
+    end_time: i64,
+    text: String,
+    speaker: Option<String>,
+) -> subtitle::Utterance {
+    subtitle::Utterance {
+        start_time,
+        end_time,
+        text,
+        words: vec![],
+        attribute: subtitle::Attribute {
+            extra: None,
+            event: None,
+            speaker,
+        },
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_captions_from_results() -> Result<()> {
+    let subtitle: subtitle::SubtitleResult = serde_json::from_value(serde_json::json!({
+        "code": 0,
+        "duration": 3.5,
+        "id": "s",
+        "message": "Success",
+        "attribute": {},
+        "utterances": [
+            {"start_time": 0, "end_time": 1200, "text": "你好", "words": [], "attribute": {"speaker": "1"}},
+            {"start_time": 1500, "end_time": 3500, "text": "欢迎使用", "words": [], "attribute": {}}
+        ]
+    }))?;
+    let record: record::RecordAsrResult = serde_json::from_value(serde_json::json!({
+        "id": "r",
+        "code": 1000,
+        "message": "Success",
+        "additions": {},
+        "utterances": [
+            {"start_time": 0, "end_time": 1200, "text": "你好", "words": [], "additions": {"speaker": "1"}},
+            {"start_time": 1500, "end_time": 3500, "text": "欢迎使用", "words": []}
+        ]
+    }))?;
+    let bigmodel: bigmodel::BigModelResult = serde_json::from_value(serde_json::json!({
+        "audio_info": {"duration": 3500},
+        "result": {
+            "text": "你好欢迎使用",
+            "utterances": [
+                {"start_time": 0, "end_time": 1200, "text": "你好", "words": [], "additions": {"speaker": "1"}},
+                {"start_time": 1500, "end_time": 3500, "text": "欢迎使用", "words": []}
+            ]
+        }
+    }))?;
+
+    assert_eq!(subtitle.to_srt(), record.to_srt());
+    assert_eq!(
+        subtitle.to_vtt(&VttSettings::default()),
+        record.to_vtt(&VttSettings::default())
+    );
+    assert_eq!(
+        subtitle.to_ass(&AssStyle::default()),
+        record.to_ass(&AssStyle::default())
+    );
+    assert_eq!(bigmodel.to_srt(), record.to_srt());
+    assert_eq!(
+        bigmodel.to_vtt(&VttSettings::default()),
+        record.to_vtt(&VttSettings::default())
+    );
+    assert_eq!(bigmodel.cues()[0].speaker(), Some("1"));
+    assert_eq!(record.cues()[0].speaker(), Some("1"));
+    assert_eq!(span(&subtitle.cues()[1]), (1500, 3500));
+    assert_eq!(clock(3_723_004), (1, 2, 3, 4));
+    Ok(())
+}
@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{
+    asr::{
+        record,
+        subtitle::{Attribute, Utterance, Word},
+    },
+    caption::{span, Cue},
+};
+use smart_default::SmartDefault;
+
+pub const NO_LINE_START: &str = ",.!?;:%)]}>，。、！？；：％）》」』】〉〕”’…・ー～";
+pub const NO_LINE_END: &str = "([{<（《「『【〈〔“‘";
+pub const SENTENCE_END: &str = ".!?。！？…";
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF
+            | 0x2E80..=0x2FDF
+            | 0x3000..=0x30FF
+            | 0x3100..=0x31FF
+            | 0x3200..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA960..=0xA97F
+            | 0xAC00..=0xD7FF
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFFEF
+            | 0x20000..=0x2FA1F
+    )
+}
+
+fn needs_space(prev: &str, next: &str) -> bool {
+    match (prev.chars().last(), next.chars().next()) {
+        (Some(a), Some(b)) => {
+            !is_cjk(a)
+                && !is_cjk(b)
+                && !a.is_whitespace()
+                && !NO_LINE_END.contains(a)
+                && !NO_LINE_START.contains(b)
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    start: i64,
+    end: i64,
+    text: String,
+}
+
+impl Token {
+    fn hangs(&self) -> bool {
+        self.text.starts_with(|c| NO_LINE_START.contains(c))
+    }
+
+    fn opens(&self) -> bool {
+        self.text.ends_with(|c| NO_LINE_END.contains(c))
+    }
+}
+
+fn join(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        if needs_space(&out, &token.text) {
+            out.push(' ');
+        }
+        out.push_str(&token.text);
+    }
+    out
+}
+
+fn interpolate(cue: &(impl Cue + ?Sized)) -> Vec<record::Word> {
+    let mut tokens: Vec<String> = vec![];
+    let mut word = String::new();
+    for c in cue.text().chars() {
+        if c.is_whitespace() || is_cjk(c) {
+            tokens.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+        }
+        if is_cjk(c) {
+            tokens.push(c.to_string());
+        } else if !c.is_whitespace() {
+            word.push(c);
+        }
+    }
+    tokens.extend((!word.is_empty()).then_some(word));
+
+    let (start, end) = span(cue);
+    let total = tokens
+        .iter()
+        .map(|t| t.chars().count() as i64)
+        .sum::<i64>()
+        .max(1);
+    let mut offset = 0;
+    tokens
+        .into_iter()
+        .map(|text| {
+            let len = text.chars().count() as i64;
+            let word = record::Word {
+                start_time: start + (end - start) * offset / total,
+                end_time: start + (end - start) * (offset + len) / total,
+                text,
+            };
+            offset += len;
+            word
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, SmartDefault, PartialEq)]
+pub struct Segmenter {
+    #[default(20)]
+    pub max_chars_per_line: usize,
+    #[default(2)]
+    pub max_lines: usize,
+    #[default(Duration::from_secs(7))]
+    pub max_duration: Duration,
+    pub min_gap: Duration,
+    #[default(true)]
+    pub break_on_sentence: bool,
+}
+
+macro_rules! impl_with {
+    ($param: ident, $typ:ty) => {
+        impl Segmenter {
+            pub fn $param(mut self, $param: $typ) -> Self {
+                self.$param = $param;
+                self
+            }
+        }
+    };
+}
+
+impl_with!(max_chars_per_line, usize);
+impl_with!(max_lines, usize);
+impl_with!(max_duration, Duration);
+impl_with!(min_gap, Duration);
+impl_with!(break_on_sentence, bool);
+
+impl Segmenter {
+    pub fn segment<'a, W: Cue + ?Sized + 'a>(
+        &self,
+        words: impl IntoIterator<Item = &'a W>,
+    ) -> Vec<Utterance> {
+        let mut utterances = self.split(words, None);
+        self.apply_min_gap(&mut utterances);
+        utterances
+    }
+
+    pub fn segment_utterances<'a, C: Cue + ?Sized + 'a>(
+        &self,
+        cues: impl IntoIterator<Item = &'a C>,
+    ) -> Vec<Utterance> {
+        let mut utterances = vec![];
+        for cue in cues {
+            let words = cue.words();
+            match words.is_empty() {
+                true => utterances.extend(self.split(&interpolate(cue), cue.speaker())),
+                false => utterances.extend(self.split(words, cue.speaker())),
+            }
+        }
+        self.apply_min_gap(&mut utterances);
+        utterances
+    }
+
+    fn split<'a, W: Cue + ?Sized + 'a>(
+        &self,
+        words: impl IntoIterator<Item = &'a W>,
+        speaker: Option<&str>,
+    ) -> Vec<Utterance> {
+        let max_duration = self.max_duration.as_millis() as i64;
+        let mut cues: Vec<Vec<Vec<Token>>> = vec![];
+        let mut lines: Vec<Vec<Token>> = vec![];
+
+        for word in words {
+            let text = word.text().trim();
+            if text.is_empty() {
+                continue;
+            }
+            let (start, end) = span(word);
+            let token = Token {
+                start,
+                end,
+                text: text.to_string(),
+            };
+
+            let cue_start = lines.first().and_then(|line| line.first()).map(|t| t.start);
+            if cue_start.is_some_and(|s| !token.hangs() && end - s > max_duration) {
+                cues.push(std::mem::take(&mut lines));
+            }
+
+            let fits = lines.last().map(|line| {
+                let current = join(line);
+                let width = current.chars().count()
+                    + needs_space(&current, &token.text) as usize
+                    + token.text.chars().count();
+                token.hangs() || width <= self.max_chars_per_line
+            });
+            let sentence_end = token.text.ends_with(|c| SENTENCE_END.contains(c));
+            match (fits, lines.last_mut()) {
+                (Some(true), Some(line)) => line.push(token),
+                (_, line) => {
+                    let mut next = vec![];
+                    if let Some(line) = line.filter(|l| l.len() > 1 && l[l.len() - 1].opens()) {
+                        next.extend(line.pop());
+                    }
+                    next.push(token);
+                    if lines.len() >= self.max_lines.max(1) {
+                        cues.push(std::mem::take(&mut lines));
+                    }
+                    lines.push(next);
+                }
+            }
+
+            if self.break_on_sentence && sentence_end {
+                cues.push(std::mem::take(&mut lines));
+            }
+        }
+        if !lines.is_empty() {
+            cues.push(lines);
+        }
+
+        let attribute = || Attribute {
+            extra: None,
+            event: None,
+            speaker: speaker.map(str::to_string),
+        };
+        cues.into_iter()
+            .filter(|lines| !lines.is_empty())
+            .map(|lines| {
+                let tokens = lines.iter().flatten().collect::<Vec<_>>();
+                Utterance {
+                    start_time: tokens[0].start,
+                    end_time: tokens.iter().map(|t| t.end).max().unwrap_or_default(),
+                    text: lines.iter().map(|l| join(l)).collect::<Vec<_>>().join("\n"),
+                    words: tokens
+                        .iter()
+                        .map(|t| Word {
+                            attribute: attribute(),
+                            start_time: t.start,
+                            end_time: t.end,
+                            text: t.text.clone(),
+                        })
+                        .collect(),
+                    attribute: attribute(),
+                }
+            })
+            .collect()
+    }
+
+    fn apply_min_gap(&self, utterances: &mut [Utterance]) {
+        let min_gap = self.min_gap.as_millis() as i64;
+        let mut last = HashMap::new();
+        for i in 0..utterances.len() {
+            let speaker = utterances[i].attribute.speaker.clone();
+            let Some(prev) = last.insert(speaker, i) else {
+                continue;
+            };
+            let (head, tail) = utterances.split_at_mut(i);
+            let (current, next) = (&mut head[prev], &mut tail[0]);
+            let end = next.start_time - min_gap;
+            if current.end_time <= end {
+                continue;
+            }
+            if end > current.start_time {
+                current.end_time = end;
+                for word in &mut current.words {
+                    word.start_time = word.start_time.min(end);
+                    word.end_time = word.end_time.min(end);
+                }
+            } else {
+                // Trimming would leave nothing of the earlier cue; push the
+                // later one back instead so they never overlap.
+                let shift = current.end_time - end;
+                next.start_time += shift;
+                next.end_time += shift;
+                for word in &mut next.words {
+                    word.start_time += shift;
+                    word.end_time += shift;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_segmenter() {
+    use crate::asr::record;
+    use crate::caption::Captions;
+
+    let words = |text: &[&str], step: i64| {
+        text.iter()
+            .enumerate()
+            .map(|(i, text)| record::Word {
+                start_time: i as i64 * step,
+                end_time: i as i64 * step + step - 50,
+                text: text.to_string(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let cjk = words(
+        &[
+            "今天", "天气", "很", "好", "，", "我们", "去", "公园", "散步", "吧", "。", "好",
+        ],
+        300,
+    );
+    let utterances = Segmenter::default()
+        .max_chars_per_line(6)
+        .max_lines(1)
+        .segment(&cjk);
+    let texts = utterances
+        .iter()
+        .map(|u| u.text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        vec!["今天天气很好，", "我们去公园", "散步吧。", "好"]
+    );
+    assert_eq!(
+        (utterances[0].start_time, utterances[0].end_time),
+        (0, 1450)
+    );
+    assert_eq!(utterances[1].words.len(), 3);
+
+    let latin = words(
+        &["Hello", "world,", "this", "is", "a", "(long)", "test."],
+        500,
+    );
+    let utterances = Segmenter::default().max_chars_per_line(12).segment(&latin);
+    let texts = utterances
+        .iter()
+        .map(|u| u.text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, vec!["Hello world,\nthis is a", "(long) test."]);
+
+    let quoted = words(&["他", "说", "“", "好", "”"], 100);
+    let utterances = Segmenter::default().max_chars_per_line(3).segment(&quoted);
+    assert_eq!(utterances[0].text, "他说\n“好”");
+
+    let utterances = Segmenter::default()
+        .max_duration(Duration::from_millis(1000))
+        .min_gap(Duration::from_millis(100))
+        .break_on_sentence(false)
+        .segment(&cjk);
+    assert!(utterances
+        .iter()
+        .all(|u| u.words.last().unwrap().end_time - u.start_time <= 1000));
+    assert_eq!(utterances[0].end_time, utterances[1].start_time - 100);
+    assert_eq!(
+        utterances[0].words.last().unwrap().end_time,
+        utterances[0].end_time
+    );
+
+    let result = record::RecordAsrResult {
+        id: "r".into(),
+        code: 1000,
+        additions: serde_json::from_str("{}").unwrap(),
+        message: "Success".into(),
+        text: None,
+        utterances: vec![record::Utterance {
+            start_time: 0,
+            end_time: 3550,
+            text: "今天天气很好，我们去公园散步吧。好".into(),
+            words: cjk,
+            additions: None,
+        }],
+    };
+    let resegmented = result.resegment(&Segmenter::default().max_chars_per_line(6));
+    assert_eq!(resegmented[0].text, "今天天气很好，\n我们去公园");
+    assert_eq!(resegmented.len(), 3);
+
+    let speaker = |speaker: &str, words: Vec<record::Word>| record::Utterance {
+        start_time: words[0].start_time,
+        end_time: words.last().unwrap().end_time,
+        text: String::new(),
+        words,
+        additions: Some(record::UAddtions {
+            event: None,
+            speaker: Some(speaker.into()),
+        }),
+    };
+    let mut answer = words(&["好", "的"], 300);
+    answer.iter_mut().for_each(|w| {
+        w.start_time += 1000;
+        w.end_time += 1000;
+    });
+    let dialog = record::RecordAsrResult {
+        utterances: vec![
+            speaker("1", words(&["你", "好", "吗"], 300)),
+            speaker("2", answer),
+        ],
+        ..result
+    };
+    let resegmented = dialog.resegment(&Segmenter::default().break_on_sentence(false));
+    let cues = resegmented
+        .iter()
+        .map(|u| (u.text.as_str(), u.attribute.speaker.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(cues, vec![("你好吗", Some("1")), ("好的", Some("2"))]);
+    assert_eq!(
+        resegmented[1].words[0].attribute.speaker.as_deref(),
+        Some("2")
+    );
+
+    let overlap = record::RecordAsrResult {
+        utterances: vec![
+            speaker("1", words(&["你", "好", "吗"], 1000)),
+            speaker("2", words(&["好", "的"], 300)),
+            speaker(
+                "1",
+                vec![record::Word {
+                    start_time: 2900,
+                    end_time: 3500,
+                    text: "嗯".into(),
+                }],
+            ),
+            speaker(
+                "1",
+                vec![record::Word {
+                    start_time: 2950,
+                    end_time: 3400,
+                    text: "对".into(),
+                }],
+            ),
+        ],
+        ..dialog.clone()
+    };
+    let resegmented = overlap.resegment(&Segmenter::default().min_gap(Duration::from_millis(100)));
+    let spans = resegmented
+        .iter()
+        .map(|u| (u.start_time, u.end_time))
+        .collect::<Vec<_>>();
+    assert_eq!(spans, vec![(0, 2800), (0, 550), (2900, 3500), (3600, 4050)]);
+    assert_eq!(resegmented[0].words[2].end_time, 2800);
+    assert_eq!(resegmented[3].words[0].start_time, 3600);
+
+    let mut untimed = dialog.clone();
+    untimed.utterances.push(record::Utterance {
+        start_time: 2000,
+        end_time: 2600,
+        text: " 世界 ".into(),
+        words: vec![],
+        additions: None,
+    });
+    let resegmented = untimed.resegment(&Segmenter::default());
+    assert_eq!(resegmented.len(), 3);
+    let last = &resegmented[2];
+    assert_eq!(
+        (last.text.as_str(), last.start_time, last.end_time),
+        ("世界", 2000, 2600)
+    );
+    assert_eq!(last.words.len(), 2);
+    assert_eq!(
+        (last.words[0].end_time, last.words[1].start_time),
+        (2300, 2300)
+    );
+
+    let long = record::Utterance {
+        start_time: 0,
+        end_time: 6000,
+        text: "the quick brown fox jumps over the lazy dog again and again today".into(),
+        words: vec![],
+        additions: None,
+    };
+    let resegmented = Segmenter::default().segment_utterances([&long]);
+    let texts = resegmented
+        .iter()
+        .map(|u| u.text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        texts,
+        vec![
+            "the quick brown fox\njumps over the lazy",
+            "dog again and again\ntoday"
+        ]
+    );
+    assert_eq!(resegmented[1].end_time, 6000);
+    assert!(resegmented[0].end_time <= resegmented[1].start_time);
+}
@@ -0,0 +1,127 @@
+use std::fmt::Write;
+
+use crate::{
+    asr::subtitle::Utterance,
+    caption::{
+        clock, lines, parse_clock, parse_error, parse_timing, source_lines, span, utterance, Cue,
+    },
+    error::*,
+};
+
+pub fn timestamp(ms: i64) -> String {
+    let (h, m, s, ms) = clock(ms.max(0));
+    format!("{:02}:{:02}:{:02},{:03}", h, m, s, ms)
+}
+
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    parse_clock(s, ',', 3, false).or_else(|| parse_clock(s, '.', 3, false))
+}
+
+pub fn to_srt<'a, C: Cue + 'a>(cues: impl IntoIterator<Item = &'a C>) -> String {
+    let mut out = String::new();
+    let mut index = 0;
+    for cue in cues {
+        let text = lines(cue.text()).collect::<Vec<_>>().join("\n");
+        if text.is_empty() {
+            continue;
+        }
+        index += 1;
+        let (start, end) = span(cue);
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            index,
+            timestamp(start),
+            timestamp(end),
+            text
+        );
+    }
+    out
+}
+
+pub fn parse(input: &str) -> Result<Vec<Utterance>> {
+    let mut utterances = vec![];
+    let mut lines = source_lines(input).peekable();
+    while let Some((line_no, line)) = lines.next() {
+        let index = line.trim();
+        if index.is_empty() {
+            continue;
+        }
+        if !index.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(parse_error(
+                line_no,
+                line,
+                line.len() - line.trim_start().len(),
+                format!("expected cue index, found {:?}", index),
+            ));
+        }
+        let (timing_no, timing) = lines
+            .next_if(|(_, l)| !l.trim().is_empty())
+            .unwrap_or((line_no + 1, ""));
+        let (start, end) = parse_timing(timing_no, timing, parse_timestamp)?;
+        let mut text = vec![];
+        while let Some((_, l)) = lines.next_if(|(_, l)| !l.trim().is_empty()) {
+            text.push(l.trim());
+        }
+        utterances.push(utterance(start, end, text.join("\n"), None));
+    }
+    Ok(utterances)
+}
+
+#[cfg(test)]
+#[test]
+fn test_srt_writer() {
+    use crate::asr::subtitle::{Attribute, Utterance};
+
+    let cue = |start_time, end_time, text: &str| Utterance {
+        start_time,
+        end_time,
+        text: text.into(),
+        words: vec![],
+        attribute: Attribute {
+            extra: None,
+            event: None,
+            speaker: None,
+        },
+    };
+
+    assert_eq!(timestamp(3_723_004), "01:02:03,004");
+    assert_eq!(timestamp(-5), "00:00:00,000");
+    assert_eq!(
+        to_srt(&[
+            cue(0, 1200, "你好"),
+            cue(1300, 1400, "  "),
+            cue(1500, 1400, " 第一行 \n第二行"),
+        ]),
+        "1\n00:00:00,000 --> 00:00:01,200\n你好\n\n\
+         2\n00:00:01,500 --> 00:00:01,500\n第一行\n第二行\n\n"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_srt_parser() -> Result<()> {
+    let input = "\u{feff}1\r\n00:00:00,000 --> 00:00:01,200\r\n你好\r\n\r\n\
+                 2\n01:02:03.004 --> 01:02:04,000 X1:10\n第一行\n 第二行 \n";
+    let utterances = parse(input)?;
+    assert_eq!(utterances.len(), 2);
+    assert_eq!(utterances[0].end_time, 1200);
+    assert_eq!(utterances[1].start_time, 3_723_004);
+    assert_eq!(utterances[1].text, "第一行\n第二行");
+    assert_eq!(to_srt(&utterances), to_srt(&parse(&to_srt(&utterances))?));
+
+    let position = |input: &str| match parse(input) {
+        Err(Error::CaptionParse { line, column, .. }) => (line, column),
+        r => panic!("unexpected {:?}", r.map(|u| u.len())),
+    };
+    assert_eq!(position("1\n00:00:01,000 --> 00:00:00,500\n"), (2, 18));
+    assert_eq!(position("1\n00:00:01,000 -> 00:00:02,000\n"), (2, 1));
+    assert_eq!(position("1\n00:00:01 --> 00:00:02,000\n"), (2, 1));
+    assert_eq!(position("\n\n  一\n"), (3, 3));
+    assert_eq!(position("1\n"), (2, 1));
+    assert_eq!(
+        position("1\n999999999999999999:00:00,000 --> 999999999999999999:00:01,000\n"),
+        (2, 1)
+    );
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use std::fmt::Write;
+
+use crate::{
+    asr::subtitle::Utterance,
+    caption::{
+        clock, lines, parse_clock, parse_error, parse_timing, source_lines, span, strip_markup,
+        utterance, Cue,
+    },
+    error::*,
+};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VttSettings {
+    pub vertical: Option<String>,
+    pub line: Option<String>,
+    pub position: Option<String>,
+    pub size: Option<String>,
+    pub align: Option<String>,
+    pub speakers: bool,
+}
+
+impl VttSettings {
+    pub fn cue_settings(&self) -> String {
+        [
+            ("vertical", &self.vertical),
+            ("line", &self.line),
+            ("position", &self.position),
+            ("size", &self.size),
+            ("align", &self.align),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some(format!("{}:{}", key, value.as_ref()?)))
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+macro_rules! impl_with {
+    ($param: ident) => {
+        impl VttSettings {
+            pub fn $param(mut self, $param: impl Into<String>) -> Self {
+                self.$param = Some($param.into());
+                self
+            }
+        }
+    };
+}
+
+impl_with!(vertical);
+impl_with!(line);
+impl_with!(position);
+impl_with!(size);
+impl_with!(align);
+
+impl VttSettings {
+    pub fn speakers(mut self, speakers: bool) -> Self {
+        self.speakers = speakers;
+        self
+    }
+}
+
+pub fn timestamp(ms: i64) -> String {
+    let (h, m, s, ms) = clock(ms.max(0));
+    format!("{:02}:{:02}:{:02}.{:03}", h, m, s, ms)
+}
+
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    parse_clock(s, '.', 3, true)
+}
+
+pub fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn to_vtt<'a, C: Cue + 'a>(
+    cues: impl IntoIterator<Item = &'a C>,
+    settings: &VttSettings,
+) -> String {
+    let cue_settings = settings.cue_settings();
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        let text = lines(cue.text()).map(escape).collect::<Vec<_>>().join("\n");
+        if text.is_empty() {
+            continue;
+        }
+        let (start, end) = span(cue);
+        let _ = write!(out, "{} --> {}", timestamp(start), timestamp(end));
+        if !cue_settings.is_empty() {
+            let _ = write!(out, " {}", cue_settings);
+        }
+        match cue.speaker().filter(|_| settings.speakers) {
+            Some(speaker) => {
+                let _ = write!(out, "\n<v {}>{}\n\n", escape(speaker), text);
+            }
+            None => {
+                let _ = write!(out, "\n{}\n\n", text);
+            }
+        }
+    }
+    out
+}
+
+fn voice(line: &str) -> (Option<String>, &str) {
+    let Some(rest) = line.strip_prefix("<v") else {
+        return (None, line);
+    };
+    match rest.find('>') {
+        Some(gt) if rest.starts_with([' ', '.']) => {
+            let speaker = rest[..gt]
+                .split_once(' ')
+                .map(|(_, name)| unescape(name.trim()))
+                .filter(|name| !name.is_empty());
+            (speaker, &rest[gt + 1..])
+        }
+        _ => (None, line),
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<Utterance>> {
+    let mut lines = source_lines(input).peekable();
+    match lines.next() {
+        Some((_, l)) if l == "WEBVTT" || l.starts_with("WEBVTT ") || l.starts_with("WEBVTT\t") => {}
+        Some((line_no, l)) => return Err(parse_error(line_no, l, 0, "missing WEBVTT header")),
+        None => return Err(parse_error(1, "", 0, "missing WEBVTT header")),
+    }
+    while lines.next_if(|(_, l)| !l.trim().is_empty()).is_some() {}
+
+    let mut utterances = vec![];
+    while let Some((mut line_no, mut line)) = lines.next() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !line.contains("-->") {
+            if matches!(
+                line.split_whitespace().next(),
+                Some("NOTE" | "STYLE" | "REGION")
+            ) {
+                while lines.next_if(|(_, l)| !l.trim().is_empty()).is_some() {}
+                continue;
+            }
+            (line_no, line) = lines
+                .next_if(|(_, l)| !l.trim().is_empty())
+                .unwrap_or((line_no + 1, ""));
+        }
+        let (start, end) = parse_timing(line_no, line, parse_timestamp)?;
+        let mut speaker = None;
+        let mut text = vec![];
+        while let Some((_, l)) = lines.next_if(|(_, l)| !l.trim().is_empty()) {
+            let (voice, l) = voice(l.trim());
+            speaker = speaker.or(voice);
+            text.push(unescape(strip_markup(l, '<', '>').trim()));
+        }
+        utterances.push(utterance(start, end, text.join("\n"), speaker));
+    }
+    Ok(utterances)
+}
+
+#[cfg(test)]
+#[test]
+fn test_vtt_writer() {
+    use crate::asr::record::{UAddtions, Utterance};
+
+    let cues = [
+        Utterance {
+            start_time: 61_000,
+            end_time: 62_345,
+            text: "a < b & c".into(),
+            words: vec![],
+            additions: Some(UAddtions {
+                event: None,
+                speaker: Some("2".into()),
+            }),
+        },
+        Utterance {
+            start_time: 3_600_000,
+            end_time: 3_601_000,
+            text: "end".into(),
+            words: vec![],
+            additions: None,
+        },
+    ];
+
+    assert_eq!(
+        to_vtt(&cues, &VttSettings::default()),
+        "WEBVTT\n\n\
+         00:01:01.000 --> 00:01:02.345\na &lt; b &amp; c\n\n\
+         01:00:00.000 --> 01:00:01.000\nend\n\n"
+    );
+
+    let settings = VttSettings::default()
+        .line("90%")
+        .align("center")
+        .speakers(true);
+    assert_eq!(escape("a --> b"), "a --&gt; b");
+    assert_eq!(settings.cue_settings(), "line:90% align:center");
+    assert_eq!(
+        to_vtt(&cues[..1], &settings),
+        "WEBVTT\n\n\
+         00:01:01.000 --> 00:01:02.345 line:90% align:center\n<v 2>a &lt; b &amp; c\n\n"
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn test_vtt_parser() -> Result<()> {
+    let input = "WEBVTT - exported\nKind: captions\n\n\
+                 NOTE edited by hand\n00:00:09.000 --> 00:00:10.000\n\n\
+                 intro\n00:01.000 --> 00:02.500 line:90% align:center\n<v.loud Alice Smith>a &lt; <b>b</b></v>\n\n\
+                 01:00:00.000 --> 01:00:01.000\nend\n";
+    let utterances = parse(input)?;
+    assert_eq!(utterances.len(), 2);
+    assert_eq!(
+        (utterances[0].start_time, utterances[0].end_time),
+        (1000, 2500)
+    );
+    assert_eq!(utterances[0].text, "a < b");
+    assert_eq!(
+        utterances[0].attribute.speaker.as_deref(),
+        Some("Alice Smith")
+    );
+    assert_eq!(utterances[1].start_time, 3_600_000);
+
+    let settings = VttSettings::default().speakers(true);
+    let output = to_vtt(&utterances, &settings);
+    assert_eq!(output, to_vtt(&parse(&output)?, &settings));
+
+    let arrows = [utterance(0, 1000, "1 --> 2\n-->".into(), None)];
+    let output = to_vtt(&arrows, &VttSettings::default());
+    assert_eq!(output.matches("-->").count(), 1);
+    let parsed = parse(&output)?;
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(parsed[0].text, "1 --> 2\n-->");
+
+    let position = |input: &str| match parse(input) {
+        Err(Error::CaptionParse { line, column, .. }) => (line, column),
+        r => panic!("unexpected {:?}", r.map(|u| u.len())),
+    };
+    assert_eq!(position("WEBVT\n"), (1, 1));
+    assert_eq!(position("WEBVTT\n\nid\n00:01.000 --> 00:61.000\n"), (4, 15));
+    assert_eq!(position("WEBVTT\n\nid\n"), (4, 1));
+    Ok(())
+}
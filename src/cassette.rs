@@ -0,0 +1,490 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    error::*,
+    transport::{BoxFuture, Transport},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{HeaderMap, StatusCode};
+use reqwest::{Request, Response};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+use url::Url;
+
+pub const REDACTED: &str = "REDACTED";
+
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "x-security-token",
+    "x-api-access-key",
+    "x-api-app-key",
+];
+
+const SENSITIVE_FIELDS: &[&str] = &[
+    "token",
+    "access_token",
+    "access_key_id",
+    "secret_access_key",
+    "session_token",
+    "AccessKeyId",
+    "SecretAccessKey",
+    "SessionToken",
+];
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub base64: bool,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+impl RecordedRequest {
+    pub fn from_request(req: &Request) -> Self {
+        Self {
+            method: req.method().to_string(),
+            url: normalize_url(req.url()),
+            headers: redact_headers(req.headers()),
+            body: req.body().and_then(|b| b.as_bytes()).map(redact_body),
+        }
+    }
+
+    fn matches(&self, other: &RecordedRequest) -> bool {
+        self.method == other.method && self.url == other.url && self.body == other.body
+    }
+}
+
+impl RecordedResponse {
+    pub fn into_response(self) -> Result<Response> {
+        let mut builder = http::Response::builder().status(
+            StatusCode::from_u16(self.status)
+                .map_err(|_| Error::Cassette(format!("invalid status {}", self.status)))?,
+        );
+        for (k, v) in self.headers.iter() {
+            builder = builder.header(k, v);
+        }
+        let body = match self.base64 {
+            true => STANDARD
+                .decode(&self.body)
+                .map_err(|e| Error::Cassette(e.to_string()))?,
+            false => self.body.into_bytes(),
+        };
+        Ok(builder
+            .body(body)
+            .map_err(|e| Error::Cassette(e.to_string()))?
+            .into())
+    }
+}
+
+pub enum CassetteTransport {
+    Record {
+        inner: Arc<dyn Transport>,
+        path: PathBuf,
+        cassette: Arc<Mutex<Cassette>>,
+        saved: Arc<Mutex<usize>>,
+    },
+    Replay {
+        path: PathBuf,
+        interactions: Mutex<Vec<Option<Interaction>>>,
+    },
+}
+
+impl CassetteTransport {
+    pub fn record(inner: impl Transport + 'static, path: impl Into<PathBuf>) -> Self {
+        Self::Record {
+            inner: Arc::new(inner),
+            path: path.into(),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+            saved: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        Ok(Self::Replay {
+            path,
+            interactions: Mutex::new(cassette.interactions.into_iter().map(Some).collect()),
+        })
+    }
+
+    pub fn remaining(&self) -> usize {
+        match self {
+            Self::Record { .. } => 0,
+            Self::Replay { interactions, .. } => interactions
+                .lock()
+                .expect("cassette lock")
+                .iter()
+                .filter(|i| i.is_some())
+                .count(),
+        }
+    }
+}
+
+impl Transport for CassetteTransport {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let recorded = RecordedRequest::from_request(&req);
+            match self {
+                Self::Record {
+                    inner,
+                    path,
+                    cassette,
+                    saved,
+                } => {
+                    let rep = inner.execute(req).await?;
+                    let status = rep.status();
+                    let original = rep.headers().clone();
+                    let headers = redact_headers(&original);
+                    let body = rep.bytes().await?;
+                    let (text, base64) = match std::str::from_utf8(&body) {
+                        Ok(_) => (redact_body(&body), false),
+                        Err(_) => (STANDARD.encode(&body), true),
+                    };
+                    let snapshot = {
+                        let mut cassette = cassette.lock().expect("cassette lock");
+                        cassette.interactions.push(Interaction {
+                            request: recorded,
+                            response: RecordedResponse {
+                                status: status.as_u16(),
+                                headers,
+                                body: text,
+                                base64,
+                            },
+                        });
+                        cassette.clone()
+                    };
+                    let (path, saved) = (path.clone(), saved.clone());
+                    tokio::task::spawn_blocking(move || {
+                        let mut saved = saved.lock().expect("cassette lock");
+                        let count = snapshot.interactions.len();
+                        if count > *saved {
+                            snapshot.save(&path)?;
+                            *saved = count;
+                            debug!("recorded {} interactions to {:?}", count, path);
+                        }
+                        Ok::<_, Error>(())
+                    })
+                    .await
+                    .map_err(|e| Error::Cassette(e.to_string()))??;
+
+                    let mut builder = http::Response::builder().status(status);
+                    if let Some(headers) = builder.headers_mut() {
+                        *headers = original;
+                    }
+                    Ok(builder
+                        .body(body)
+                        .map_err(|e| Error::Cassette(e.to_string()))?
+                        .into())
+                }
+                Self::Replay { path, interactions } => {
+                    let response = {
+                        let mut interactions = interactions.lock().expect("cassette lock");
+                        interactions
+                            .iter_mut()
+                            .find(|i| i.as_ref().is_some_and(|i| i.request.matches(&recorded)))
+                            .and_then(Option::take)
+                            .map(|i| i.response)
+                    };
+                    match response {
+                        Some(response) => response.into_response(),
+                        None => {
+                            warn!(
+                                "no interaction in {:?} for {} {}",
+                                path, recorded.method, recorded.url
+                            );
+                            Err(Error::Cassette(format!(
+                                "no recorded interaction for {} {}",
+                                recorded.method, recorded.url
+                            )))
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn normalize_url(url: &Url) -> String {
+    let mut url = url.clone();
+    let mut pairs = url
+        .query_pairs()
+        .map(|(k, v)| match is_sensitive(&k) {
+            true => (k.into_owned(), REDACTED.to_string()),
+            false => (k.into_owned(), v.into_owned()),
+        })
+        .collect::<Vec<_>>();
+    pairs.sort();
+    match pairs.is_empty() {
+        true => url.set_query(None),
+        false => {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+    url.to_string()
+}
+
+fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_FIELDS.contains(&name)
+}
+
+fn redact_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(k, _)| k.as_str() != "x-date" && k.as_str() != "date")
+        .map(|(k, v)| {
+            let value = match SENSITIVE_HEADERS.contains(&k.as_str()) {
+                true => REDACTED.to_string(),
+                false => String::from_utf8_lossy(v.as_bytes()).into_owned(),
+            };
+            (k.to_string(), value)
+        })
+        .collect()
+}
+
+fn redact_body(body: &[u8]) -> String {
+    if let Ok(mut value) = serde_json::from_slice::<Value>(body) {
+        redact_value(&mut value);
+        return value.to_string();
+    }
+    match std::str::from_utf8(body) {
+        Ok(text) => text.to_string(),
+        Err(_) => format!("sha256:{}", hex::encode(Sha256::digest(body))),
+    }
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                match is_sensitive(k) && v.is_string() {
+                    true => *v = Value::String(REDACTED.into()),
+                    false => redact_value(v),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+fn cassette_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cassettes")
+        .join(name)
+}
+
+#[cfg(test)]
+fn replay_client(name: &str) -> Result<crate::client::Client> {
+    crate::client::Client::builder()
+        .access_token("token")
+        .transport(CassetteTransport::replay(cassette_path(name))?)
+        .build()
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_cassette_replay_subtitle() -> Result<()> {
+    use crate::asr::subtitle::{SubtitleRequest, SubtitleSource};
+    use crate::poll::PollPolicy;
+
+    let client = replay_client("subtitle.json")?;
+
+    let rep = SubtitleRequest::builder()
+        .appid("100")
+        .words_per_line("15")
+        .max_lines("1")
+        .source(SubtitleSource::Binary {
+            typ: "mp3".into(),
+            data: vec![0xff, 0xfb, 0x90, 0x64],
+        })
+        .build()?
+        .call(&client)
+        .await?;
+    assert_eq!(rep.id, "a9b2c9e8-1c1e-4bbb-b2a8-9f4ecf3b6b39");
+
+    let result = rep
+        .waiting_result("100", &client, PollPolicy::fixed(std::time::Duration::ZERO))
+        .await?;
+    assert_eq!(result.utterances.len(), 2);
+    assert_eq!(result.utterances[1].text, "欢迎使用火山引擎");
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_cassette_replay_record_asr() -> Result<()> {
+    use crate::asr::record::RecordAsrRequest;
+    use crate::poll::PollPolicy;
+
+    let client = replay_client("record_asr.json")?;
+
+    let rep = RecordAsrRequest::builder()
+        .appid("100")
+        .token("secret-token")
+        .cluster("volc_auc_common")
+        .uid("uid")
+        .url("https://example.com/test.mp3")
+        .format("mp3")
+        .build()?
+        .call(&client)
+        .await?;
+    assert_eq!(rep.id, "fc5aa03e-823c-4eaf-a7d6-6e6a5d4b9f37");
+
+    let result = rep
+        .clone()
+        .waiting_result(&client, PollPolicy::fixed(std::time::Duration::ZERO))
+        .await?;
+    assert_eq!(result.text.as_deref(), Some("你好，世界。"));
+    assert_eq!(result.utterances[0].words.len(), 4);
+
+    let e = rep
+        .waiting_result(&client, PollPolicy::fixed(std::time::Duration::ZERO))
+        .await
+        .unwrap_err();
+    assert_eq!(e.api().and_then(|e| e.code.as_deref()), Some("1013"));
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_cassette_record_redacts() -> Result<()> {
+    use crate::{client::Client, transport::MockResponse};
+    use http::Method;
+
+    let path =
+        std::env::temp_dir().join(format!("volcengine-cassette-{}.json", std::process::id()));
+    let client = Client::builder()
+        .access_token("secret-token")
+        .layer({
+            let path = path.clone();
+            move |inner| CassetteTransport::record(inner, path)
+        })
+        .transport(crate::transport::json_mock(|_| {
+            MockResponse::json(
+                serde_json::json!({"resp": {"code": 1000, "message": "ok", "id": "job"}}),
+            )
+            .header("x-tt-logid", "log")
+        }))
+        .build()?;
+
+    let body = r#"{"appid": "100", "token": "secret-token", "id": "job"}"#;
+    client
+        .call(
+            Method::POST,
+            "/api/v1/auc/query",
+            vec![],
+            vec![],
+            Some(body.into()),
+        )
+        .await?;
+
+    let cassette = Cassette::load(&path)?;
+    let _ = std::fs::remove_file(&path);
+    let saved = serde_json::to_string(&cassette)?;
+    assert!(!saved.contains("secret-token"));
+    assert_eq!(
+        cassette.interactions[0].request.headers["authorization"],
+        REDACTED
+    );
+    assert_eq!(
+        cassette.interactions[0].response.headers["x-tt-logid"],
+        "log"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_cassette_record_redacts_response() -> Result<()> {
+    use crate::client::Client;
+    use http::Method;
+
+    let path = std::env::temp_dir().join(format!(
+        "volcengine-cassette-sts-{}.json",
+        std::process::id()
+    ));
+    let client = Client::builder()
+        .access_token("token")
+        .layer({
+            let path = path.clone();
+            move |inner| CassetteTransport::record(inner, path)
+        })
+        .transport(crate::transport::json_mock(|_| {
+            serde_json::json!({
+                "ResponseMetadata": {"RequestId": "req", "Action": "AssumeRole"},
+                "Result": {"Credentials": {
+                    "AccessKeyId": "AKTPsecretak",
+                    "SecretAccessKey": "secret-sk",
+                    "SessionToken": "secret-session",
+                    "ExpiredTime": "2030-01-01T00:00:00Z",
+                }}
+            })
+        }))
+        .build()?;
+
+    let rep = client
+        .call(Method::GET, "/?Action=AssumeRole", vec![], vec![], None)
+        .await?;
+    let received: Value = serde_json::from_slice(&rep.bytes().await?)?;
+    assert_eq!(
+        received["Result"]["Credentials"]["SecretAccessKey"],
+        "secret-sk"
+    );
+
+    let cassette = Cassette::load(&path)?;
+    let _ = std::fs::remove_file(&path);
+    let saved = serde_json::to_string(&cassette)?;
+    for secret in ["AKTPsecretak", "secret-sk", "secret-session"] {
+        assert!(!saved.contains(secret), "{} leaked", secret);
+    }
+    let body: Value = serde_json::from_str(&cassette.interactions[0].response.body)?;
+    assert_eq!(body["Result"]["Credentials"]["SessionToken"], REDACTED);
+    assert_eq!(
+        body["Result"]["Credentials"]["ExpiredTime"],
+        "2030-01-01T00:00:00Z"
+    );
+    Ok(())
+}
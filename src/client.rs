@@ -1,43 +1,90 @@
-use crate::error::*;
+use crate::{
+    auth::{Anonymous, Authorizer, BearerToken, CredentialsChain, CredentialsProvider},
+    error::*,
+    retry::RetryPolicy,
+    transport::{Layer, Transport},
+};
 
-use http::header::{self, HeaderValue};
-use http::{HeaderName, Method};
-use reqwest::{Body, Request, Response};
+use std::sync::Arc;
+use std::time::Duration;
+
+use http::header::{HeaderMap, HeaderValue};
+use http::{HeaderName, Method, StatusCode};
+use reqwest::{Body, Certificate, Proxy, Request, Response};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use smart_default::SmartDefault;
+use tracing::{error, trace, warn};
 use url::Url;
 
-#[derive(SmartDefault)]
+pub const SPEECH_ENDPOINT: &str = "https://openspeech.bytedance.com";
+pub const OPEN_API_ENDPOINT: &str = "https://open.volcengineapi.com";
+pub const ARK_ENDPOINT: &str = "https://ark.cn-beijing.volces.com";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Service {
+    Speech,
+    OpenApi,
+    Ark,
+}
+
+#[derive(Clone, SmartDefault)]
 pub struct Client {
-    #[default(Url::parse("https://openspeech.bytedance.com").unwrap())]
+    #[default(Url::parse(SPEECH_ENDPOINT).unwrap())]
     pub base_url: Url,
-    pub access_token: String,
+    #[default(Url::parse(OPEN_API_ENDPOINT).unwrap())]
+    pub open_api_url: Url,
+    #[default(Url::parse(ARK_ENDPOINT).unwrap())]
+    pub ark_url: Url,
+    #[default(Arc::new(Anonymous))]
+    pub auth: Arc<dyn Authorizer>,
     #[default(reqwest::Client::new())]
     pub client: reqwest::Client,
+    #[default(Arc::new(reqwest::Client::new()))]
+    pub transport: Arc<dyn Transport>,
+    pub retry: RetryPolicy,
 }
 
 impl Client {
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn service_url(&self, service: Service) -> &Url {
+        match service {
+            Service::Speech => &self.base_url,
+            Service::OpenApi => &self.open_api_url,
+            Service::Ark => &self.ark_url,
+        }
+    }
+
     pub fn from_env() -> Result<Self> {
-        let _ = dotenv::dotenv()?;
-        Ok(Self {
-            access_token: std::env::var("VOLCENGINE_ACCESS_TOKEN")?,
-            ..Self::default()
-        })
+        let _ = dotenv::dotenv();
+        Self::from_provider(CredentialsChain::standard())
     }
 
     pub fn from_env_file(filename: impl AsRef<str>) -> Result<Self> {
         let _ = dotenv::from_filename(filename.as_ref())?;
+        Self::from_provider(CredentialsChain::standard())
+    }
+
+    pub fn from_provider(provider: impl CredentialsProvider + 'static) -> Result<Self> {
+        provider.credentials()?.require_access_token()?;
+        let client = reqwest::Client::new();
         Ok(Self {
-            access_token: std::env::var("VOLCENGINE_ACCESS_TOKEN")?,
+            auth: Arc::new(BearerToken::from_provider(provider)),
+            transport: Arc::new(client.clone()),
+            client,
             ..Self::default()
         })
     }
 
+    pub fn access_token(&self) -> Option<String> {
+        self.auth.access_token()
+    }
+
     pub fn authorize(&self, req: &mut Request) -> Result<()> {
-        req.headers_mut().insert(
-            header::AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer; {}", self.access_token))?,
-        );
-        Ok(())
+        self.auth.authorize(req)
     }
 
     pub async fn call(
@@ -48,28 +95,443 @@ impl Client {
         headers: Vec<(HeaderName, HeaderValue)>,
         body: Option<Body>,
     ) -> Result<Response> {
-        let mut builder = self
-            .client
-            .request(method, self.base_url.join(uri.as_ref())?);
+        let idempotent = method.is_idempotent();
+        self.call_with(method, uri, queries, headers, body, idempotent)
+            .await
+    }
+
+    pub async fn call_idempotent(
+        &self,
+        method: Method,
+        uri: impl AsRef<str>,
+        queries: Vec<(String, String)>,
+        headers: Vec<(HeaderName, HeaderValue)>,
+        body: Option<Body>,
+    ) -> Result<Response> {
+        self.call_with(method, uri, queries, headers, body, true)
+            .await
+    }
+
+    async fn call_with(
+        &self,
+        method: Method,
+        uri: impl AsRef<str>,
+        queries: Vec<(String, String)>,
+        headers: Vec<(HeaderName, HeaderValue)>,
+        body: Option<Body>,
+        idempotent: bool,
+    ) -> Result<Response> {
+        let mut url = self.base_url.join(uri.as_ref())?;
+
+        if !queries.is_empty() {
+            url.query_pairs_mut().extend_pairs(queries);
+        }
+
+        let mut req = Request::new(method, url);
 
         for (k, v) in headers {
-            builder = builder.header(k, v);
+            req.headers_mut().append(k, v);
+        }
+
+        *req.body_mut() = body;
+
+        self.authorize(&mut req)?;
+
+        self.execute(req, idempotent).await
+    }
+
+    async fn execute(&self, req: Request, idempotent: bool) -> Result<Response> {
+        let retryable = idempotent || self.retry.retry_non_idempotent;
+        let mut backoff = self.retry.backoff();
+        let mut retries = 0;
+
+        loop {
+            let attempt = match retryable && retries < self.retry.max_retries {
+                true => req.try_clone(),
+                false => None,
+            };
+
+            let Some(attempt) = attempt else {
+                return self.transport.execute(req).await;
+            };
+
+            let delay = match self.transport.execute(attempt).await {
+                Ok(rep) if !RetryPolicy::is_retryable_status(rep.status()) => return Ok(rep),
+                Ok(rep) => {
+                    let delay = RetryPolicy::retry_after(rep.headers())
+                        .map(|d| d.min(self.retry.max_retry_after))
+                        .unwrap_or_else(|| backoff.next_delay());
+                    warn!(
+                        "{} {} returned {}, retry in {:?}",
+                        req.method(),
+                        req.url(),
+                        rep.status(),
+                        delay
+                    );
+                    delay
+                }
+                Err(Error::Http(e)) if RetryPolicy::is_retryable_error(&e) => {
+                    let delay = backoff.next_delay();
+                    warn!(
+                        "{} {} failed: {}, retry in {:?}",
+                        req.method(),
+                        req.url(),
+                        e,
+                        delay
+                    );
+                    delay
+                }
+                Err(e) => return Err(e),
+            };
+
+            retries += 1;
+            tokio::time::sleep(delay).await;
         }
+    }
+}
+
+pub struct RawResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl RawResponse {
+    pub async fn read(rep: Response) -> Result<Self> {
+        let status = rep.status();
+        let headers = rep.headers().clone();
+        let body = rep.bytes().await?.to_vec();
 
-        if let Some(body) = body {
-            builder = builder.body(body);
+        let text = match serde_json::from_slice::<Value>(&body) {
+            Ok(value) => serde_json::to_string_pretty(&value)?,
+            Err(_) => String::from_utf8_lossy(&body).into_owned(),
+        };
+        for l in text.lines() {
+            match status.is_success() {
+                true => trace!("REP: {}", l),
+                false => error!("REP: {}", l),
+            }
         }
 
-        for (k, v) in queries {
-            builder = builder.query(&[(k, v)]);
+        Ok(Self {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    pub fn api_error(&self) -> Error {
+        ApiError::new(self.status, &self.headers, &self.body).into()
+    }
+
+    pub fn error_for_status(self) -> Result<Self> {
+        match self.status.is_success() {
+            true => Ok(self),
+            false => Err(self.api_error()),
         }
+    }
 
-        let mut req = builder.build()?;
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).map_err(|e| self.unexpected(e))
+    }
 
-        self.authorize(&mut req)?;
+    pub fn parse<T: DeserializeOwned>(&self, value: Value) -> Result<T> {
+        serde_json::from_value(value).map_err(|e| self.unexpected(e))
+    }
 
-        let rep = self.client.execute(req).await?;//.error_for_status()?;
+    fn unexpected(&self, e: serde_json::Error) -> Error {
+        ApiError::new(self.status, &self.headers, &self.body)
+            .with_message(format!("unexpected response body: {}", e))
+            .into()
+    }
+}
+
+#[derive(SmartDefault)]
+pub struct ClientBuilder {
+    #[default(Arc::new(Anonymous))]
+    pub auth: Arc<dyn Authorizer>,
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub default_headers: HeaderMap,
+    pub root_certificates: Vec<Vec<u8>>,
+    #[default(SPEECH_ENDPOINT.into())]
+    pub speech_endpoint: String,
+    #[default(OPEN_API_ENDPOINT.into())]
+    pub open_api_endpoint: String,
+    #[default(ARK_ENDPOINT.into())]
+    pub ark_endpoint: String,
+    pub retry: RetryPolicy,
+    pub transport: Option<Arc<dyn Transport>>,
+    pub layers: Vec<Layer>,
+}
+
+impl ClientBuilder {
+    pub fn access_token(self, access_token: impl Into<String>) -> Self {
+        self.auth(BearerToken::new(access_token))
+    }
+
+    pub fn credentials(self, provider: impl CredentialsProvider + 'static) -> Self {
+        self.auth(BearerToken::from_provider(provider))
+    }
+
+    pub fn auth(mut self, auth: impl Authorizer + 'static) -> Self {
+        self.auth = Arc::new(auth);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    pub fn root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem.into());
+        self
+    }
+
+    pub fn endpoint(mut self, service: Service, url: impl Into<String>) -> Self {
+        let url = url.into();
+        match service {
+            Service::Speech => self.speech_endpoint = url,
+            Service::OpenApi => self.open_api_endpoint = url,
+            Service::Ark => self.ark_endpoint = url,
+        }
+        self
+    }
+
+    pub fn base_url(self, url: impl Into<String>) -> Self {
+        self.endpoint(Service::Speech, url)
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 
-        Ok(rep)
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
     }
+
+    pub fn layer<T, F>(mut self, layer: F) -> Self
+    where
+        T: Transport + 'static,
+        F: FnOnce(Arc<dyn Transport>) -> T + 'static,
+    {
+        self.layers.push(Box::new(move |inner| {
+            Arc::new(layer(inner)) as Arc<dyn Transport>
+        }));
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let Self {
+            auth,
+            connect_timeout,
+            read_timeout,
+            timeout,
+            proxy,
+            user_agent,
+            default_headers,
+            root_certificates,
+            speech_endpoint,
+            open_api_endpoint,
+            ark_endpoint,
+            retry,
+            transport,
+            layers,
+        } = self;
+
+        if transport.is_some() {
+            let conflict = [
+                (connect_timeout.is_some(), "connect_timeout"),
+                (read_timeout.is_some(), "read_timeout"),
+                (timeout.is_some(), "timeout"),
+                (proxy.is_some(), "proxy"),
+                (user_agent.is_some(), "user_agent"),
+                (!default_headers.is_empty(), "default_headers"),
+                (!root_certificates.is_empty(), "root_certificates"),
+            ];
+            if let Some((_, name)) = conflict.into_iter().find(|(set, _)| *set) {
+                return Err(Error::ClientBuild(name));
+            }
+        }
+
+        let mut builder = reqwest::Client::builder().default_headers(default_headers);
+
+        if let Some(timeout) = connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        if let Some(user_agent) = user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        for pem in root_certificates {
+            builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+        }
+
+        let client = builder.build()?;
+        let mut transport = match transport {
+            Some(transport) => transport,
+            None => Arc::new(client.clone()),
+        };
+
+        for layer in layers {
+            transport = layer(transport);
+        }
+
+        Ok(Client {
+            base_url: Url::parse(&speech_endpoint)?,
+            open_api_url: Url::parse(&open_api_endpoint)?,
+            ark_url: Url::parse(&ark_endpoint)?,
+            auth,
+            client,
+            transport,
+            retry,
+        })
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_client_builder() -> Result<()> {
+    let client = Client::builder()
+        .access_token("token")
+        .base_url("http://127.0.0.1:8080")
+        .endpoint(Service::Ark, "http://127.0.0.1:8081/")
+        .connect_timeout(Duration::from_secs(3))
+        .read_timeout(Duration::from_secs(30))
+        .user_agent("volcengine-rs-test")
+        .proxy("http://127.0.0.1:3128")
+        .default_header(
+            HeaderName::from_static("x-test"),
+            HeaderValue::from_static("1"),
+        )
+        .build()?;
+
+    assert_eq!(client.access_token().as_deref(), Some("token"));
+    assert_eq!(Client::default().access_token(), None);
+    assert!(Client::from_provider(crate::auth::Credentials::token("  ")).is_err());
+    let mut req = Request::new(Method::GET, "http://127.0.0.1:8080/".parse()?);
+    let e = Client::builder()
+        .access_token("")
+        .build()?
+        .authorize(&mut req)
+        .unwrap_err();
+    assert!(matches!(e, Error::MissingCredential("access_token")));
+    assert!(req.headers().get(http::header::AUTHORIZATION).is_none());
+    assert_eq!(
+        client.service_url(Service::Speech).as_str(),
+        "http://127.0.0.1:8080/"
+    );
+    assert_eq!(
+        client.service_url(Service::OpenApi).as_str(),
+        "https://open.volcengineapi.com/"
+    );
+    assert_eq!(
+        client.service_url(Service::Ark).as_str(),
+        "http://127.0.0.1:8081/"
+    );
+    assert!(Client::builder().base_url("not a url").build().is_err());
+    assert!(matches!(
+        Client::builder()
+            .timeout(Duration::from_secs(3))
+            .transport(crate::transport::from_fn(|_req: Request| async move {
+                Ok(http::Response::new("").into())
+            }))
+            .build(),
+        Err(Error::ClientBuild("timeout"))
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_client_mock_transport() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let client = Client::builder()
+        .access_token("token")
+        .retry(RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+        .transport(crate::transport::from_fn(move |req: Request| {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                assert_eq!(req.headers()[http::header::AUTHORIZATION], "Bearer; token");
+                assert_eq!(req.url().query(), Some("appid=1&id=job"));
+                let status = match n {
+                    0 => StatusCode::SERVICE_UNAVAILABLE,
+                    _ => StatusCode::OK,
+                };
+                Ok(http::Response::builder()
+                    .status(status)
+                    .body("{}")
+                    .unwrap()
+                    .into())
+            }
+        }))
+        .layer(crate::transport::Traced::new)
+        .build()?;
+
+    let queries = vec![("appid".into(), "1".into()), ("id".into(), "job".into())];
+    let rep = client
+        .call(
+            Method::GET,
+            "/api/v1/vc/query",
+            queries.clone(),
+            vec![],
+            None,
+        )
+        .await?;
+    assert_eq!(rep.status(), StatusCode::OK);
+    assert_eq!(calls.swap(0, Ordering::SeqCst), 2);
+
+    let rep = client
+        .call(Method::POST, "/api/v1/vc/submit", queries, vec![], None)
+        .await?;
+    assert_eq!(rep.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    Ok(())
 }
@@ -1,3 +1,7 @@
+use crate::asr::record::AucStatus;
+use http::{HeaderMap, StatusCode};
+use serde_json::Value;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error
 {
@@ -7,8 +11,10 @@ pub enum Error
     Http(#[from] reqwest::Error),
     #[error("{0}")]
     Url(#[from] url::ParseError),
-    #[error("failed to build subtitle request")]
-    SubtitleRequestBuild,
+    #[error("failed to build subtitle request: missing {0}")]
+    SubtitleRequestBuild(&'static str),
+    #[error("failed to build align request: missing {0}")]
+    AlignRequestBuild(&'static str),
     #[error("{0}")]
     Json(#[from] serde_json::Error),
     #[error("{0}")]
@@ -19,10 +25,252 @@ pub enum Error
     NoExtension,
     #[error("{0}")]
     Io(#[from] std::io::Error),
-    #[error("record asr return error")]
-    RecordAsrResponse,
-    #[error("record asr request build error")]
-    RecordRequestBuild
+    #[error("{0}")]
+    Api(Box<ApiError>),
+    #[error("failed to build client: {0} has no effect with a custom transport")]
+    ClientBuild(&'static str),
+    #[error("failed to build record asr request: missing {0}")]
+    RecordRequestBuild(&'static str),
+    #[error("polling job {id} timed out after {attempts} attempts ({elapsed:?})")]
+    Timeout {
+        id: String,
+        attempts: u32,
+        elapsed: std::time::Duration,
+    },
+    #[error("no credentials found")]
+    NoCredentials,
+    #[error("missing credential: {0}")]
+    MissingCredential(&'static str),
+    #[error("invalid profile config at line {line}: {message}")]
+    Profile { line: usize, message: String },
+    #[error("sts refresh window {refresh_before:?} must be shorter than the credential lifetime {lifetime:?}")]
+    StsRefreshWindow {
+        refresh_before: std::time::Duration,
+        lifetime: std::time::Duration,
+    },
+    #[error("credentials expired at {0}")]
+    CredentialsExpired(String),
+    #[error("cassette error: {0}")]
+    Cassette(String),
+    #[error("request body must be buffered to be signed")]
+    UnsignableBody,
+    #[error("polling job {id} cancelled")]
+    Cancelled { id: String },
+    #[error("failed to build bigmodel asr request: missing {0}")]
+    BigModelRequestBuild(&'static str),
+    #[error("failed to build flash asr request: missing {0}")]
+    FlashRequestBuild(&'static str),
+    #[error("invalid boosting table: {0}")]
+    BoostingTable(String),
+    #[error("invalid correct table: {0}")]
+    CorrectTable(String),
+    #[error("invalid presign expiry {0:?}, must be between 1s and 7 days")]
+    PresignExpiry(std::time::Duration),
+    #[error("caption parse error at line {line}, column {column}: {message}")]
+    CaptionParse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("job store error: {0}")]
+    Store(String),
+    #[cfg(feature = "sqlite")]
+    #[error("{0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+impl Error {
+    pub fn api(&self) -> Option<&ApiError> {
+        match self {
+            Error::Api(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Api(e) => e.is_retryable(),
+            Error::Http(e) => e.is_connect() || e.is_timeout(),
+            _ => false,
+        }
+    }
+
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            Error::Api(e) => e.is_auth_error(),
+            Error::NoCredentials | Error::MissingCredential(_) | Error::CredentialsExpired(_) => {
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(value: ApiError) -> Self {
+        Error::Api(Box::new(value))
+    }
+}
+
+const RETRYABLE_CODES: &[&str] = &[
+    "1003",
+    "1005",
+    "55000031",
+    "RequestLimitExceeded",
+    "FlowLimitExceeded",
+    "ServiceUnavailable",
+    "ServiceUnavailableTemp",
+    "InternalError",
+    "InternalServiceError",
+];
+
+const AUTH_CODES: &[&str] = &[
+    "1002",
+    "AccessDenied",
+    "InvalidAccessKey",
+    "InvalidAuthorization",
+    "InvalidCredential",
+    "InvalidSecretToken",
+    "MissingAuthenticationToken",
+    "SignatureDoesNotMatch",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: Option<String>,
+    pub message: String,
+    pub log_id: Option<String>,
+    pub body: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        let body = String::from_utf8_lossy(body).into_owned();
+        let value = serde_json::from_str::<Value>(&body).unwrap_or_default();
+
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        let as_string = |v: &Value| match v {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+
+        let (code, message) = [&value["resp"], &value["ResponseMetadata"]["Error"], &value]
+            .into_iter()
+            .find_map(|v| {
+                let code = as_string(&v["code"]).or_else(|| as_string(&v["Code"]))?;
+                let message = as_string(&v["message"]).or_else(|| as_string(&v["Message"]));
+                Some((Some(code), message))
+            })
+            .unwrap_or_else(|| (header("x-api-status-code"), header("x-api-message")));
+
+        let log_id = header("x-tt-logid")
+            .or_else(|| header("x-tos-request-id"))
+            .or_else(|| {
+                [&value["ResponseMetadata"]["RequestId"], &value["RequestId"]]
+                    .into_iter()
+                    .find_map(|v| v.as_str().map(|v| v.to_string()))
+            });
+
+        Self {
+            status,
+            code,
+            message: message
+                .unwrap_or_else(|| status.canonical_reason().unwrap_or_default().to_string()),
+            log_id,
+            body,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    pub fn auc_status(&self) -> Option<AucStatus> {
+        self.code
+            .as_deref()?
+            .parse::<i32>()
+            .ok()
+            .map(AucStatus::from)
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.status == StatusCode::TOO_MANY_REQUESTS
+            || self.status.is_server_error()
+            || self
+                .code
+                .as_deref()
+                .is_some_and(|c| RETRYABLE_CODES.contains(&c))
+    }
+
+    pub fn is_auth_error(&self) -> bool {
+        self.status == StatusCode::UNAUTHORIZED
+            || self.status == StatusCode::FORBIDDEN
+            || self
+                .code
+                .as_deref()
+                .is_some_and(|c| AUTH_CODES.contains(&c))
+    }
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "api error: status={}", self.status)?;
+        if let Some(code) = &self.code {
+            write!(f, ", code={}", code)?;
+        }
+        if let Some(log_id) = &self.log_id {
+            write!(f, ", log_id={}", log_id)?;
+        }
+        write!(f, ", message={}", self.message)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+#[test]
+fn test_api_error_from_body() {
+    use http::HeaderValue;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-tt-logid", HeaderValue::from_static("20240828123045ABC"));
+
+    let e = ApiError::new(
+        StatusCode::OK,
+        &headers,
+        br#"{"resp": {"id": "job", "code": 1013, "message": "audio is silent"}}"#,
+    );
+    assert_eq!(e.code.as_deref(), Some("1013"));
+    assert_eq!(e.message, "audio is silent");
+    assert_eq!(e.log_id.as_deref(), Some("20240828123045ABC"));
+    assert_eq!(e.auc_status(), Some(AucStatus::SilentAudio));
+    assert!(!e.is_retryable() && !e.is_auth_error());
+
+    let e = ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        &HeaderMap::new(),
+        br#"{"ResponseMetadata": {"RequestId": "req", "Error": {"Code": "SignatureDoesNotMatch", "Message": "bad signature"}}}"#,
+    );
+    assert_eq!(e.code.as_deref(), Some("SignatureDoesNotMatch"));
+    assert_eq!(e.log_id.as_deref(), Some("req"));
+    assert!(Error::from(e).is_auth_error());
+
+    let e = ApiError::new(
+        StatusCode::BAD_GATEWAY,
+        &HeaderMap::new(),
+        b"<html>bad gateway</html>",
+    );
+    assert_eq!(e.code, None);
+    assert_eq!(e.message, "Bad Gateway");
+    assert_eq!(e.body, "<html>bad gateway</html>");
+    assert!(Error::from(e).is_retryable());
+}
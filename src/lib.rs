@@ -1,5 +1,16 @@
+pub mod auth;
 pub mod error;
+pub mod cassette;
 pub mod client;
 pub mod asr;
+pub mod caption;
 pub mod ffmpeg;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod openapi;
+pub mod poll;
+pub mod retry;
+pub mod store;
+pub mod tos;
+pub mod transport;
 pub mod types;
\ No newline at end of file
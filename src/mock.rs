@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::*;
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use rand::Rng;
+use serde_json::{json, Value};
+use smart_default::SmartDefault;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+#[derive(Debug, Clone, SmartDefault)]
+pub struct MockConfig {
+    #[default(1)]
+    pub queued_polls: u32,
+    #[default(1)]
+    pub processing_polls: u32,
+    pub latency: Duration,
+    pub failure: Option<(i64, String)>,
+    pub unavailable: u32,
+    pub access_token: Option<String>,
+    #[default("你好世界。欢迎使用火山引擎。".into())]
+    pub transcript: String,
+}
+
+impl MockConfig {
+    pub fn queued_polls(mut self, polls: u32) -> Self {
+        self.queued_polls = polls;
+        self
+    }
+
+    pub fn processing_polls(mut self, polls: u32) -> Self {
+        self.processing_polls = polls;
+        self
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn failure(mut self, code: i64, message: impl Into<String>) -> Self {
+        self.failure = Some((code, message.into()));
+        self
+    }
+
+    pub fn unavailable(mut self, requests: u32) -> Self {
+        self.unavailable = requests;
+        self
+    }
+
+    pub fn access_token(mut self, access_token: impl Into<String>) -> Self {
+        self.access_token = Some(access_token.into());
+        self
+    }
+
+    pub fn transcript(mut self, transcript: impl Into<String>) -> Self {
+        self.transcript = transcript.into();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    Record,
+    Subtitle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobPhase {
+    Queued,
+    Processing,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockJob {
+    pub kind: JobKind,
+    pub appid: String,
+    pub polls: u32,
+}
+
+struct MockState {
+    config: MockConfig,
+    jobs: Mutex<HashMap<String, MockJob>>,
+    requests: AtomicU32,
+}
+
+impl MockState {
+    fn phase(&self, polls: u32) -> JobPhase {
+        let MockConfig {
+            queued_polls,
+            processing_polls,
+            failure,
+            ..
+        } = &self.config;
+        match polls {
+            n if n < *queued_polls => JobPhase::Queued,
+            n if n < queued_polls + processing_polls => JobPhase::Processing,
+            _ if failure.is_some() => JobPhase::Failed,
+            _ => JobPhase::Done,
+        }
+    }
+
+    fn submit(&self, kind: JobKind, appid: &str) -> String {
+        let id = new_id();
+        self.jobs.lock().expect("mock jobs lock").insert(
+            id.clone(),
+            MockJob {
+                kind,
+                appid: appid.into(),
+                polls: 0,
+            },
+        );
+        info!("mock submitted {:?} job {} for appid {}", kind, id, appid);
+        id
+    }
+
+    fn poll(
+        &self,
+        kind: JobKind,
+        id: &str,
+        appid: Option<&str>,
+        blocking: bool,
+    ) -> Option<JobPhase> {
+        let mut jobs = self.jobs.lock().expect("mock jobs lock");
+        let job = jobs.get_mut(id).filter(|j| j.kind == kind)?;
+        if appid.is_some_and(|appid| appid != job.appid) {
+            return None;
+        }
+        let phase = match blocking {
+            true => self.phase(u32::MAX),
+            false => self.phase(job.polls),
+        };
+        job.polls += 1;
+        debug!("mock job {} polled {} times: {:?}", id, job.polls, phase);
+        Some(phase)
+    }
+
+    async fn gate(&self, headers: &HeaderMap) -> Option<Response> {
+        if !self.config.latency.is_zero() {
+            tokio::time::sleep(self.config.latency).await;
+        }
+        if self.requests.fetch_add(1, Ordering::SeqCst) < self.config.unavailable {
+            return Some(
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, "0")],
+                    "service unavailable",
+                )
+                    .into_response(),
+            );
+        }
+        if let Some(token) = &self.config.access_token {
+            let expected = format!("Bearer; {}", token);
+            let actual = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok());
+            if actual != Some(expected.as_str()) {
+                return Some(
+                    (
+                        StatusCode::UNAUTHORIZED,
+                        Json(json!({"code": 1002, "message": "invalid access token"})),
+                    )
+                        .into_response(),
+                );
+            }
+        }
+        None
+    }
+}
+
+pub struct MockServer {
+    pub addr: SocketAddr,
+    state: Arc<MockState>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start(config: MockConfig) -> Result<Self> {
+        Self::bind("127.0.0.1:0", config).await
+    }
+
+    pub async fn bind(addr: &str, config: MockConfig) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(MockState {
+            config,
+            jobs: Mutex::new(HashMap::new()),
+            requests: AtomicU32::new(0),
+        });
+        let router = router(state.clone());
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                tracing::error!("mock server stopped: {}", e);
+            }
+        });
+        info!("mock volcengine speech server listening on {}", addr);
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn jobs(&self) -> HashMap<String, MockJob> {
+        self.state.jobs.lock().expect("mock jobs lock").clone()
+    }
+
+    pub async fn wait(mut self) {
+        let _ = (&mut self.handle).await;
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn router(state: Arc<MockState>) -> Router {
+    Router::new()
+        .route("/api/v1/auc/submit", post(auc_submit))
+        .route("/api/v1/auc/query", post(auc_query))
+        .route("/api/v1/vc/submit", post(vc_submit))
+        .route("/api/v1/vc/query", get(vc_query))
+        .with_state(state)
+}
+
+fn new_id() -> String {
+    let mut rng = rand::thread_rng();
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        rng.gen::<u32>(),
+        rng.gen::<u16>(),
+        rng.gen::<u16>(),
+        rng.gen::<u16>(),
+        rng.gen::<u64>() & 0xffff_ffff_ffff
+    )
+}
+
+fn auc_resp(body: Value) -> Response {
+    Json(json!({ "resp": body })).into_response()
+}
+
+async fn auc_submit(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(rep) = state.gate(&headers).await {
+        return rep;
+    }
+    let body: Value = serde_json::from_slice(&body).unwrap_or_default();
+    let appid = body["app"]["appid"].as_str().unwrap_or_default();
+    let url = body["audio"]["url"].as_str().unwrap_or_default();
+    if appid.is_empty() || url.is_empty() {
+        return auc_resp(json!({"id": "", "code": 1001, "message": "invalid request params"}));
+    }
+    let id = state.submit(JobKind::Record, appid);
+    auc_resp(json!({"id": id, "code": 1000, "message": "Success"}))
+}
+
+async fn auc_query(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(rep) = state.gate(&headers).await {
+        return rep;
+    }
+    let body: Value = serde_json::from_slice(&body).unwrap_or_default();
+    let id = body["id"].as_str().unwrap_or_default();
+    let appid = body["appid"].as_str();
+    match state.poll(JobKind::Record, id, appid, false) {
+        None => auc_resp(json!({"id": id, "code": 1001, "message": "job not found"})),
+        Some(JobPhase::Queued) => auc_resp(json!({"id": id, "code": 2001, "message": "Queued"})),
+        Some(JobPhase::Processing) => {
+            auc_resp(json!({"id": id, "code": 2000, "message": "Processing"}))
+        }
+        Some(JobPhase::Failed) => {
+            let (code, message) = state.config.failure.clone().unwrap_or_default();
+            auc_resp(json!({"id": id, "code": code, "message": message}))
+        }
+        Some(JobPhase::Done) => {
+            let utterances = utterances(&state.config.transcript, false);
+            let duration = utterances
+                .last()
+                .and_then(|u| u["end_time"].as_i64())
+                .unwrap_or_default();
+            auc_resp(json!({
+                "id": id,
+                "code": 1000,
+                "message": "Success",
+                "text": state.config.transcript,
+                "additions": {"duration": duration.to_string()},
+                "utterances": utterances,
+            }))
+        }
+    }
+}
+
+async fn vc_submit(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(rep) = state.gate(&headers).await {
+        return rep;
+    }
+    let Some(appid) = params.get("appid") else {
+        return Json(json!({"id": "", "code": 1001, "message": "missing appid"})).into_response();
+    };
+    if body.is_empty() {
+        return Json(json!({"id": "", "code": 1001, "message": "empty audio"})).into_response();
+    }
+    let id = state.submit(JobKind::Subtitle, appid);
+    Json(json!({"id": id, "code": 0, "message": "Success"})).into_response()
+}
+
+async fn vc_query(
+    State(state): State<Arc<MockState>>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(rep) = state.gate(&headers).await {
+        return rep;
+    }
+    let id = params.get("id").map(String::as_str).unwrap_or_default();
+    let blocking = params.get("blocking").is_some_and(|b| b == "1");
+    match state.poll(
+        JobKind::Subtitle,
+        id,
+        params.get("appid").map(String::as_str),
+        blocking,
+    ) {
+        None => Json(json!({"id": id, "code": 1001, "message": "job not found"})).into_response(),
+        Some(JobPhase::Queued) | Some(JobPhase::Processing) => {
+            Json(json!({"id": id, "code": 2000, "message": "Running"})).into_response()
+        }
+        Some(JobPhase::Failed) => {
+            let (code, message) = state.config.failure.clone().unwrap_or_default();
+            Json(json!({"id": id, "code": code, "message": message})).into_response()
+        }
+        Some(JobPhase::Done) => {
+            let utterances = utterances(&state.config.transcript, true);
+            let duration = utterances
+                .last()
+                .and_then(|u| u["end_time"].as_i64())
+                .unwrap_or_default();
+            Json(json!({
+                "id": id,
+                "code": 0,
+                "message": "Success",
+                "duration": duration as f64 / 1000.0,
+                "attribute": {
+                    "extra": {
+                        "asr_service": "mock",
+                        "caption_type": "speech",
+                        "is_mandarin": "True",
+                        "is_speech": "True",
+                        "language": "zh-CN",
+                    }
+                },
+                "utterances": utterances,
+            }))
+            .into_response()
+        }
+    }
+}
+
+fn utterances(transcript: &str, subtitle: bool) -> Vec<Value> {
+    const WORD_MS: i64 = 240;
+    const GAP_MS: i64 = 400;
+
+    let mut utterances = vec![];
+    let mut start = 200;
+
+    for sentence in transcript.split_inclusive(['。', '！', '？', '.', '!', '?']) {
+        let text = sentence.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let tokens: Vec<String> = match text.contains(' ') {
+            true => text.split_whitespace().map(|w| w.to_string()).collect(),
+            false => text
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .map(|c| c.to_string())
+                .collect(),
+        };
+        let words = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let word = json!({
+                    "text": t,
+                    "start_time": start + i as i64 * WORD_MS,
+                    "end_time": start + (i as i64 + 1) * WORD_MS,
+                });
+                match subtitle {
+                    true => {
+                        let mut word = word;
+                        word["attribute"] = json!({"event": "speech"});
+                        word
+                    }
+                    false => word,
+                }
+            })
+            .collect::<Vec<_>>();
+        let end = start + tokens.len() as i64 * WORD_MS;
+        let additions = json!({"event": "speech", "speaker": "1"});
+        let mut utterance = json!({
+            "text": text,
+            "start_time": start,
+            "end_time": end,
+            "words": words,
+        });
+        match subtitle {
+            true => utterance["attribute"] = additions,
+            false => utterance["additions"] = additions,
+        }
+        utterances.push(utterance);
+        start = end + GAP_MS;
+    }
+
+    utterances
+}
+
+#[cfg(test)]
+fn mock_client(server: &MockServer) -> Result<crate::client::Client> {
+    crate::client::Client::builder()
+        .access_token("mock-token")
+        .base_url(server.url())
+        .retry(crate::retry::RetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+        .build()
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_mock_record_asr() -> Result<()> {
+    use crate::asr::record::RecordAsrRequest;
+    use crate::poll::PollPolicy;
+
+    let request = || {
+        RecordAsrRequest::builder()
+            .appid("100")
+            .token("token")
+            .cluster("volc_auc_common")
+            .uid("uid")
+            .url("https://example.com/a.mp3")
+            .build()
+    };
+    let policy = PollPolicy::fixed(Duration::from_millis(5));
+
+    let server = MockServer::start(MockConfig::default().access_token("mock-token")).await?;
+    let client = mock_client(&server)?;
+    let result = request()?
+        .call(&client)
+        .await?
+        .waiting_result(&client, policy.clone())
+        .await?;
+    assert_eq!(result.utterances.len(), 2);
+    assert_eq!(result.utterances[0].text, "你好世界。");
+    assert_eq!(server.jobs().values().next().unwrap().polls, 3);
+
+    let server = MockServer::start(MockConfig::default().failure(1013, "audio is silent")).await?;
+    let client = mock_client(&server)?;
+    let e = request()?
+        .call(&client)
+        .await?
+        .waiting_result(&client, policy.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(e.api().and_then(|e| e.code.as_deref()), Some("1013"));
+
+    let server = MockServer::start(MockConfig::default().processing_polls(100)).await?;
+    let client = mock_client(&server)?;
+    let e = request()?
+        .call(&client)
+        .await?
+        .waiting_result(&client, policy.max_attempts(3))
+        .await
+        .unwrap_err();
+    assert!(matches!(e, Error::Timeout { attempts: 3, .. }));
+
+    let server = MockServer::start(MockConfig::default().access_token("other")).await?;
+    let client = mock_client(&server)?;
+    let e = request()?.call(&client).await.unwrap_err();
+    assert!(e.is_auth_error());
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_mock_subtitle() -> Result<()> {
+    use crate::asr::subtitle::{SubtitleRequest, SubtitleSource};
+    use crate::poll::PollPolicy;
+
+    let server = MockServer::start(
+        MockConfig::default()
+            .unavailable(1)
+            .latency(Duration::from_millis(5))
+            .transcript("hello world. welcome to volcengine."),
+    )
+    .await?;
+    let client = mock_client(&server)?;
+
+    let rep = SubtitleRequest::builder()
+        .appid("100")
+        .source(SubtitleSource::Url("https://example.com/a.mp3".into()))
+        .build()?
+        .call(&client)
+        .await;
+    assert!(rep.unwrap_err().is_retryable());
+
+    let result = SubtitleRequest::builder()
+        .appid("100")
+        .source(SubtitleSource::Binary {
+            typ: "mp3".into(),
+            data: vec![0xff, 0xfb],
+        })
+        .build()?
+        .call(&client)
+        .await?
+        .waiting_result("100", &client, PollPolicy::fixed(Duration::from_millis(5)))
+        .await?;
+    assert_eq!(result.utterances.len(), 2);
+    assert_eq!(result.utterances[1].words.len(), 3);
+    Ok(())
+}
@@ -0,0 +1,496 @@
+use std::marker::PhantomData;
+
+use crate::{
+    auth::{CredentialsProvider, Signer},
+    client::{Client, RawResponse, Service},
+    error::*,
+};
+use http::{header, HeaderName, HeaderValue, Method};
+use rand::Rng;
+use reqwest::Body;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+pub const SELF_LEARNING_SERVICE: &str = "speech_saas_prod";
+pub const SELF_LEARNING_VERSION: &str = "2022-08-30";
+pub const SELF_LEARNING_REGION: &str = "cn-north-1";
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ResponseMetadata {
+    pub request_id: String,
+    pub action: String,
+    pub version: String,
+    pub service: String,
+    pub region: String,
+    pub error: Option<ResponseError>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ResponseError {
+    pub code_n: Option<i64>,
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct OpenApiResponse<T> {
+    pub response_metadata: ResponseMetadata,
+    pub result: Option<T>,
+}
+
+pub async fn call<T: DeserializeOwned>(
+    client: &Client,
+    method: Method,
+    action: &str,
+    version: &str,
+    queries: Vec<(String, String)>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Option<Body>,
+) -> Result<T> {
+    let (raw, rep) = send::<T>(client, method, action, version, queries, headers, body).await?;
+    rep.result.ok_or_else(|| match raw.api_error() {
+        Error::Api(e) => e.with_message("response has no Result").into(),
+        e => e,
+    })
+}
+
+pub async fn send<T: DeserializeOwned>(
+    client: &Client,
+    method: Method,
+    action: &str,
+    version: &str,
+    mut queries: Vec<(String, String)>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: Option<Body>,
+) -> Result<(RawResponse, OpenApiResponse<T>)> {
+    queries.insert(0, ("Version".into(), version.into()));
+    queries.insert(0, ("Action".into(), action.into()));
+
+    let rep = client
+        .call(
+            method,
+            client.service_url(Service::OpenApi).as_str(),
+            queries,
+            headers,
+            body,
+        )
+        .await?;
+
+    let raw = RawResponse::read(rep).await?;
+    let rep: OpenApiResponse<T> = raw.json()?;
+
+    if !raw.status.is_success() || rep.response_metadata.error.is_some() {
+        return Err(raw.api_error());
+    }
+
+    Ok((raw, rep))
+}
+
+pub async fn call_json<T: DeserializeOwned>(
+    client: &Client,
+    action: &str,
+    version: &str,
+    body: Value,
+) -> Result<T> {
+    call(
+        client,
+        Method::POST,
+        action,
+        version,
+        vec![],
+        vec![(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )],
+        Some(Body::from(body.to_string())),
+    )
+    .await
+}
+
+pub async fn call_delete(client: &Client, action: &str, version: &str, body: Value) -> Result<()> {
+    send::<Value>(
+        client,
+        Method::POST,
+        action,
+        version,
+        vec![],
+        vec![(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )],
+        Some(Body::from(body.to_string())),
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn call_multipart<T: DeserializeOwned>(
+    client: &Client,
+    action: &str,
+    version: &str,
+    form: Multipart,
+) -> Result<T> {
+    let (content_type, body) = form.finish()?;
+    call(
+        client,
+        Method::POST,
+        action,
+        version,
+        vec![],
+        vec![(header::CONTENT_TYPE, content_type)],
+        Some(body),
+    )
+    .await
+}
+
+pub fn self_learning_client(provider: impl CredentialsProvider + 'static) -> Result<Client> {
+    Client::builder()
+        .auth(Signer::new(
+            provider,
+            SELF_LEARNING_REGION,
+            SELF_LEARNING_SERVICE,
+        ))
+        .build()
+}
+
+pub trait SelfLearningTable: DeserializeOwned {
+    type Entry;
+    type List: DeserializeOwned;
+
+    const KIND: &'static str;
+    const FILE_NAME: &'static str;
+
+    fn validate(entries: &[Self::Entry]) -> Result<()>;
+    fn render(entries: &[Self::Entry]) -> String;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfLearningTables<T> {
+    pub app_id: String,
+    table: PhantomData<fn() -> T>,
+}
+
+impl<T: SelfLearningTable> SelfLearningTables<T> {
+    pub fn new(app_id: impl Into<String>) -> Self {
+        Self {
+            app_id: app_id.into(),
+            table: PhantomData,
+        }
+    }
+
+    fn action(verb: &str) -> String {
+        format!("{}{}Table", verb, T::KIND)
+    }
+
+    fn field(name: &str) -> String {
+        format!("{}Table{}", T::KIND, name)
+    }
+
+    fn entries<E: Into<T::Entry>>(entries: impl IntoIterator<Item = E>) -> Result<String> {
+        let entries = entries.into_iter().map(Into::into).collect::<Vec<_>>();
+        T::validate(&entries)?;
+        Ok(T::render(&entries))
+    }
+
+    fn with_id(&self, id: &str) -> Value {
+        let mut body = json!({ "AppID": self.app_id });
+        body[Self::field("ID")] = id.into();
+        body
+    }
+
+    pub async fn create<E: Into<T::Entry>>(
+        &self,
+        client: &Client,
+        name: &str,
+        entries: impl IntoIterator<Item = E>,
+    ) -> Result<T> {
+        let form = Multipart::new()
+            .text("AppID", &self.app_id)
+            .text(&Self::field("Name"), name)
+            .file(
+                "File",
+                T::FILE_NAME,
+                "text/plain",
+                Self::entries(entries)?.as_bytes(),
+            );
+        call_multipart(client, &Self::action("Create"), SELF_LEARNING_VERSION, form).await
+    }
+
+    pub async fn list(&self, client: &Client, page_number: u32, page_size: u32) -> Result<T::List> {
+        call_json(
+            client,
+            &Self::action("List"),
+            SELF_LEARNING_VERSION,
+            json!({
+                "AppID": self.app_id,
+                "PageNumber": page_number,
+                "PageSize": page_size,
+            }),
+        )
+        .await
+    }
+
+    pub async fn get(&self, client: &Client, id: &str) -> Result<T> {
+        call_json(
+            client,
+            &Self::action("Get"),
+            SELF_LEARNING_VERSION,
+            self.with_id(id),
+        )
+        .await
+    }
+
+    pub async fn update<E: Into<T::Entry>>(
+        &self,
+        client: &Client,
+        id: &str,
+        name: Option<&str>,
+        entries: Option<impl IntoIterator<Item = E>>,
+    ) -> Result<T> {
+        let mut form = Multipart::new()
+            .text("AppID", &self.app_id)
+            .text(&Self::field("ID"), id);
+        if let Some(name) = name {
+            form = form.text(&Self::field("Name"), name);
+        }
+        if let Some(entries) = entries {
+            form = form.file(
+                "File",
+                T::FILE_NAME,
+                "text/plain",
+                Self::entries(entries)?.as_bytes(),
+            );
+        }
+        call_multipart(client, &Self::action("Update"), SELF_LEARNING_VERSION, form).await
+    }
+
+    pub async fn delete(&self, client: &Client, id: &str) -> Result<()> {
+        call_delete(
+            client,
+            &Self::action("Delete"),
+            SELF_LEARNING_VERSION,
+            self.with_id(id),
+        )
+        .await
+    }
+}
+
+pub struct Multipart {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Multipart {
+    pub fn new() -> Self {
+        Self {
+            boundary: format!(
+                "volcengine-{}",
+                hex::encode(rand::thread_rng().gen::<[u8; 12]>())
+            ),
+            body: vec![],
+        }
+    }
+
+    pub fn text(mut self, name: &str, value: impl AsRef<str>) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                self.boundary,
+                name,
+                value.as_ref()
+            )
+            .as_bytes(),
+        );
+        self
+    }
+
+    pub fn file(mut self, name: &str, filename: &str, content_type: &str, data: &[u8]) -> Self {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                 Content-Type: {}\r\n\r\n",
+                self.boundary, name, filename, content_type
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    pub fn content_type(&self) -> Result<HeaderValue> {
+        Ok(HeaderValue::from_str(&format!(
+            "multipart/form-data; boundary={}",
+            self.boundary
+        ))?)
+    }
+
+    pub fn finish(mut self) -> Result<(HeaderValue, Body)> {
+        let content_type = self.content_type()?;
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        Ok((content_type, Body::from(self.body)))
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn mock_transport(
+    handler: impl Fn(&reqwest::Request, &str, String) -> Value + Send + Sync + 'static,
+) -> impl crate::transport::Transport {
+    crate::transport::json_mock(move |req| {
+        let action = req
+            .url()
+            .query_pairs()
+            .find(|(k, _)| k == "Action")
+            .map(|(_, v)| v.to_string())
+            .unwrap_or_default();
+        let body = req
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| String::from_utf8_lossy(b).to_string())
+            .unwrap_or_default();
+        serde_json::json!({
+            "ResponseMetadata": {"RequestId": "r", "Action": action},
+            "Result": handler(req, &action, body),
+        })
+    })
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_self_learning_tables() -> Result<()> {
+    use crate::{
+        asr::{
+            boosting::{BoostingTables, BoostingWord},
+            correction::{CorrectRule, CorrectTables},
+        },
+        auth::Credentials,
+    };
+
+    let client = Client::builder()
+        .auth(Signer::new(
+            Credentials::access_key("AK", "SK"),
+            SELF_LEARNING_REGION,
+            SELF_LEARNING_SERVICE,
+        ))
+        .transport(mock_transport(|req, action, body| {
+            assert!(req.headers()[header::AUTHORIZATION]
+                .to_str()
+                .unwrap()
+                .contains("/cn-north-1/speech_saas_prod/request"));
+            assert!(req.url().query().unwrap().contains("Version=2022-08-30"));
+            let (verb, kind) = ["Create", "List", "Get", "Update", "Delete"]
+                .into_iter()
+                .find_map(|verb| Some((verb, action.strip_prefix(verb)?.strip_suffix("Table")?)))
+                .unwrap();
+            let field = |name: &str| format!("{}Table{}", kind, name);
+            let part =
+                |name: &str, value: &str| format!("name=\"{}\"\r\n\r\n{}\r\n", field(name), value);
+            let mut table = json!({ "WordCount": 2, "Preview": ["preview"] });
+            table[field("ID")] = "t1".into();
+            table[field("Name")] = "brands".into();
+            match verb {
+                "Create" => {
+                    assert!(req.headers()[header::CONTENT_TYPE]
+                        .to_str()
+                        .unwrap()
+                        .starts_with("multipart/form-data; boundary="));
+                    assert!(body.contains(&part("Name", "brands")));
+                    let file = match kind {
+                        "Boosting" => "filename=\"words.txt\"",
+                        _ => "filename=\"rules.txt\"",
+                    };
+                    assert!(body.contains(file));
+                    assert!(
+                        body.contains("\r\n\r\n火山引擎|5\n豆包\n\r\n")
+                            || body.contains("\r\n\r\n火山音箭|火山引擎\n逗包|豆包\n\r\n")
+                    );
+                    table
+                }
+                "List" => {
+                    assert_eq!(body, r#"{"AppID":"100","PageNumber":1,"PageSize":10}"#);
+                    let mut list = json!({ "PageNumber": 1, "PageSize": 10, "Total": 1 });
+                    list[format!("{}Tables", kind)] = json!([table]);
+                    list
+                }
+                "Get" => {
+                    assert_eq!(body, format!(r#"{{"AppID":"100","{}":"t1"}}"#, field("ID")));
+                    table
+                }
+                "Update" => {
+                    assert!(body.contains(&part("ID", "t1")));
+                    if !body.contains("filename=") {
+                        assert!(body.contains(&part("Name", "products")));
+                        table[field("Name")] = "products".into();
+                    } else {
+                        assert!(!body.contains(&field("Name")));
+                    }
+                    table
+                }
+                _ => {
+                    assert_eq!(body, format!(r#"{{"AppID":"100","{}":"t1"}}"#, field("ID")));
+                    Value::Null
+                }
+            }
+        }))
+        .build()?;
+
+    let boosting = BoostingTables::new("100");
+    let table = boosting
+        .create(
+            &client,
+            "brands",
+            [("火山引擎", 5).into(), BoostingWord::new("豆包")],
+        )
+        .await?;
+    assert_eq!((table.id.as_str(), table.word_count), ("t1", 2));
+    let list = boosting.list(&client, 1, 10).await?;
+    assert_eq!(list.total, 1);
+    assert_eq!(list.boosting_tables[0].name, "brands");
+    assert_eq!(boosting.get(&client, "t1").await?.preview, vec!["preview"]);
+    let table = boosting
+        .update(&client, "t1", Some("products"), None::<Vec<BoostingWord>>)
+        .await?;
+    assert_eq!(table.name, "products");
+    assert!(matches!(
+        boosting
+            .update(&client, "t1", None, Some(Vec::<BoostingWord>::new()))
+            .await,
+        Err(Error::BoostingTable(_))
+    ));
+    boosting.delete(&client, "t1").await?;
+
+    let correct = CorrectTables::new("100");
+    let table = correct
+        .create(
+            &client,
+            "brands",
+            [("火山音箭", "火山引擎"), ("逗包", "豆包")],
+        )
+        .await?;
+    assert_eq!((table.id.as_str(), table.word_count), ("t1", 2));
+    let list = correct.list(&client, 1, 10).await?;
+    assert_eq!(list.correct_tables[0].id, "t1");
+    assert_eq!(correct.get(&client, "t1").await?.preview, vec!["preview"]);
+    let table = correct
+        .update(&client, "t1", None, Some([("火山音箭", "火山引擎")]))
+        .await?;
+    assert_eq!(table.name, "brands");
+    assert!(matches!(
+        correct
+            .update(&client, "t1", None, Some(Vec::<CorrectRule>::new()))
+            .await,
+        Err(Error::CorrectTable(_))
+    ));
+    correct.delete(&client, "t1").await?;
+    Ok(())
+}
@@ -0,0 +1,478 @@
+use std::future::Future;
+use std::time::Duration;
+
+use crate::error::*;
+use futures::{stream, Stream, StreamExt};
+use rand::Rng;
+use smart_default::SmartDefault;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{trace, warn};
+
+#[derive(Debug, Clone, SmartDefault)]
+pub struct PollPolicy {
+    pub deadline: Option<Duration>,
+    pub max_attempts: Option<u32>,
+    #[default(Duration::from_secs(1))]
+    pub interval: Duration,
+    #[default(Duration::from_secs(30))]
+    pub max_interval: Duration,
+    #[default(2.0)]
+    pub multiplier: f64,
+    #[default(0.2)]
+    pub jitter: f64,
+    pub cancel: Option<CancellationToken>,
+}
+
+impl PollPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fixed(interval: Duration) -> Self {
+        Self {
+            interval,
+            max_interval: interval,
+            multiplier: 1.0,
+            jitter: 0.0,
+            ..Self::default()
+        }
+    }
+
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn max_interval(mut self, max_interval: Duration) -> Self {
+        self.max_interval = max_interval;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn cancel(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn poller(&self, id: impl Into<String>) -> Poller {
+        Poller {
+            id: id.into(),
+            started: Instant::now(),
+            attempts: 0,
+            backoff: Backoff::new(
+                self.interval,
+                self.max_interval,
+                self.multiplier,
+                self.jitter,
+            ),
+            policy: self.clone(),
+        }
+    }
+}
+
+impl From<Duration> for PollPolicy {
+    fn from(value: Duration) -> Self {
+        Self::fixed(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Poller {
+    id: String,
+    started: Instant,
+    attempts: u32,
+    backoff: Backoff,
+    policy: PollPolicy,
+}
+
+impl Poller {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.policy
+            .deadline
+            .map(|deadline| deadline.saturating_sub(self.elapsed()))
+    }
+
+    fn timeout(&self) -> Error {
+        Error::Timeout {
+            id: self.id.clone(),
+            attempts: self.attempts,
+            elapsed: self.elapsed(),
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if let Some(token) = &self.policy.cancel {
+            if token.is_cancelled() {
+                return Err(Error::Cancelled {
+                    id: self.id.clone(),
+                });
+            }
+        }
+
+        if self.attempts > 0 {
+            if let Some(max_attempts) = self.policy.max_attempts {
+                if self.attempts >= max_attempts {
+                    return Err(self.timeout());
+                }
+            }
+
+            if let Some(deadline) = self.policy.deadline {
+                if self.elapsed() >= deadline {
+                    return Err(self.timeout());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn try_attempt(&mut self) -> Result<u32> {
+        self.check()?;
+        self.attempts += 1;
+        Ok(self.attempts)
+    }
+
+    pub fn next_delay(&mut self) -> Result<Duration> {
+        self.check()?;
+        let delay = self.backoff.next_delay();
+        match self.remaining() {
+            Some(remaining) if remaining.is_zero() => Err(self.timeout()),
+            Some(remaining) => Ok(delay.min(remaining)),
+            None => Ok(delay),
+        }
+    }
+
+    pub async fn next_attempt(&mut self) -> Result<u32> {
+        self.check()?;
+
+        if self.attempts > 0 {
+            let delay = self.next_delay()?;
+
+            trace!(
+                "polling job {} again in {:?}, attempt={}",
+                self.id,
+                delay,
+                self.attempts + 1
+            );
+
+            match &self.policy.cancel {
+                Some(token) => {
+                    tokio::select! {
+                        _ = token.cancelled() => return Err(Error::Cancelled { id: self.id.clone() }),
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
+                None => tokio::time::sleep(delay).await,
+            }
+            self.check()?;
+        }
+
+        self.attempts += 1;
+        Ok(self.attempts)
+    }
+
+    pub async fn attempt<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        let cancelled = async {
+            match &self.policy.cancel {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending().await,
+            }
+        };
+        let expired = async {
+            match self.remaining() {
+                Some(remaining) => tokio::time::sleep(remaining).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            biased;
+            result = fut => result,
+            _ = cancelled => Err(Error::Cancelled { id: self.id.clone() }),
+            _ = expired => Err(self.timeout()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus<T> {
+    Queued,
+    Processing,
+    Done(T),
+}
+
+impl<T> JobStatus<T> {
+    pub fn is_done(&self) -> bool {
+        matches!(self, JobStatus::Done(_))
+    }
+}
+
+#[derive(Debug)]
+pub enum JobEvent<T> {
+    Submitted { id: String },
+    Queued { attempt: u32 },
+    Processing { attempt: u32 },
+    Succeeded(T),
+    Failed(Error),
+}
+
+impl<T> JobEvent<T> {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, JobEvent::Succeeded(_) | JobEvent::Failed(_))
+    }
+}
+
+pub(crate) fn watch<T, F, Fut>(poller: Poller, query: F) -> impl Stream<Item = JobEvent<T>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<JobStatus<T>>>,
+{
+    let submitted = JobEvent::Submitted {
+        id: poller.id().to_string(),
+    };
+    let polling = stream::unfold(Some((poller, query)), |state| async move {
+        let (mut poller, mut query) = state?;
+        let event = loop {
+            let attempt = match poller.next_attempt().await {
+                Ok(attempt) => attempt,
+                Err(e) => break JobEvent::Failed(e),
+            };
+            match poller.attempt(query()).await {
+                Ok(JobStatus::Queued) => break JobEvent::Queued { attempt },
+                Ok(JobStatus::Processing) => break JobEvent::Processing { attempt },
+                Ok(JobStatus::Done(result)) => break JobEvent::Succeeded(result),
+                Err(e) if e.is_retryable() => warn!(
+                    "job {} query failed, retrying: {}, attempt={}",
+                    poller.id(),
+                    e,
+                    attempt
+                ),
+                Err(e) => break JobEvent::Failed(e),
+            }
+        };
+        trace!("job {} event: {}", poller.id(), event_name(&event));
+        let next = match event.is_terminal() {
+            true => None,
+            false => Some((poller, query)),
+        };
+        Some((event, next))
+    });
+    stream::once(async { submitted }).chain(polling)
+}
+
+fn event_name<T>(event: &JobEvent<T>) -> &'static str {
+    match event {
+        JobEvent::Submitted { .. } => "submitted",
+        JobEvent::Queued { .. } => "queued",
+        JobEvent::Processing { .. } => "processing",
+        JobEvent::Succeeded(_) => "succeeded",
+        JobEvent::Failed(_) => "failed",
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Backoff {
+    current: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+impl Backoff {
+    pub(crate) fn new(initial: Duration, max: Duration, multiplier: f64, jitter: f64) -> Self {
+        Self {
+            current: initial,
+            max: max.max(initial),
+            multiplier: multiplier.max(1.0),
+            jitter: jitter.clamp(0.0, 1.0),
+        }
+    }
+
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = Duration::try_from_secs_f64(self.current.as_secs_f64() * self.multiplier)
+            .unwrap_or(self.max)
+            .min(self.max);
+        match self.jitter > 0.0 {
+            true => {
+                let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+                Duration::try_from_secs_f64(delay.as_secs_f64() * factor).unwrap_or(delay)
+            }
+            false => delay,
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_backoff_overflow() {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::MAX, f64::INFINITY, 1.0);
+    assert!(backoff.next_delay() <= Duration::from_secs(2));
+    assert!(backoff.next_delay() <= Duration::MAX);
+    assert!(backoff.next_delay() <= Duration::MAX);
+
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::MAX, 1e300, 0.0);
+    assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    assert_eq!(backoff.next_delay(), Duration::MAX);
+
+    let mut backoff = Backoff::new(
+        Duration::from_secs(1),
+        Duration::from_secs(8),
+        f64::NAN,
+        0.0,
+    );
+    assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+}
+
+#[cfg(test)]
+#[tokio::test(start_paused = true)]
+async fn test_poll_policy_limits() {
+    let policy = PollPolicy::fixed(Duration::from_secs(5)).max_attempts(3);
+    let mut poller = policy.poller("job");
+    for attempt in 1..=3 {
+        assert_eq!(poller.next_attempt().await.unwrap(), attempt);
+    }
+    assert!(matches!(
+        poller.next_attempt().await,
+        Err(Error::Timeout { attempts: 3, .. })
+    ));
+
+    let policy = PollPolicy::new()
+        .interval(Duration::from_secs(4))
+        .jitter(0.0)
+        .deadline(Duration::from_secs(10));
+    let mut poller = policy.poller("job");
+    while poller.next_attempt().await.is_ok() {}
+    assert_eq!(poller.attempts(), 2);
+    assert_eq!(poller.elapsed(), Duration::from_secs(10));
+    assert_eq!(poller.remaining(), Some(Duration::ZERO));
+    assert!(matches!(
+        poller.next_attempt().await,
+        Err(Error::Timeout { attempts: 2, .. })
+    ));
+    assert_eq!(poller.elapsed(), Duration::from_secs(10));
+
+    let mut poller = PollPolicy::fixed(Duration::from_secs(1))
+        .deadline(Duration::from_secs(3))
+        .poller("job");
+    poller.next_attempt().await.unwrap();
+    let hung = poller
+        .attempt(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        })
+        .await;
+    assert!(matches!(hung, Err(Error::Timeout { attempts: 1, .. })));
+    assert_eq!(poller.elapsed(), Duration::from_secs(3));
+    assert_eq!(poller.attempt(async { Ok(7) }).await.unwrap(), 7);
+
+    let token = CancellationToken::new();
+    let policy = PollPolicy::new().cancel(token.clone());
+    let mut poller = policy.poller("job");
+    poller.next_attempt().await.unwrap();
+    token.cancel();
+    assert!(matches!(
+        poller.next_attempt().await,
+        Err(Error::Cancelled { .. })
+    ));
+}
+
+#[cfg(test)]
+#[tokio::test(start_paused = true)]
+async fn test_watch_job_events() {
+    let mut polls = 0;
+    let events = watch(
+        PollPolicy::fixed(Duration::from_secs(5)).poller("job"),
+        || {
+            polls += 1;
+            let status = match polls {
+                1 => Ok(JobStatus::Queued),
+                2 | 3 => Ok(JobStatus::Processing),
+                _ => Ok(JobStatus::Done(polls)),
+            };
+            async move { status }
+        },
+    )
+    .collect::<Vec<_>>()
+    .await;
+
+    assert!(matches!(&events[0], JobEvent::Submitted { id } if id == "job"));
+    assert!(matches!(events[1], JobEvent::Queued { attempt: 1 }));
+    assert!(matches!(events[3], JobEvent::Processing { attempt: 3 }));
+    assert!(matches!(events[4], JobEvent::Succeeded(4)));
+    assert_eq!(events.len(), 5);
+
+    let events = watch(PollPolicy::new().max_attempts(2).poller("job"), || async {
+        Ok::<_, Error>(JobStatus::<()>::Processing)
+    })
+    .collect::<Vec<_>>()
+    .await;
+    assert_eq!(events.len(), 4);
+    assert!(matches!(
+        events[3],
+        JobEvent::Failed(Error::Timeout { attempts: 2, .. })
+    ));
+    let mut polls = 0;
+    let events = watch(
+        PollPolicy::fixed(Duration::from_secs(5)).poller("job"),
+        || {
+            polls += 1;
+            let status = match polls {
+                1 => Err(ApiError::new(
+                    http::StatusCode::OK,
+                    &http::HeaderMap::new(),
+                    br#"{"resp": {"code": 1003, "message": "rate limited"}}"#,
+                )
+                .into()),
+                2 => Ok(JobStatus::Processing),
+                _ => Err(ApiError::new(
+                    http::StatusCode::OK,
+                    &http::HeaderMap::new(),
+                    br#"{"resp": {"code": 1013, "message": "audio is silent"}}"#,
+                )
+                .into()),
+            };
+            async move { status }
+        },
+    )
+    .collect::<Vec<_>>()
+    .await;
+    assert_eq!(events.len(), 3);
+    assert!(matches!(events[1], JobEvent::Processing { attempt: 2 }));
+    assert!(matches!(
+        &events[2],
+        JobEvent::<()>::Failed(e) if e.api().and_then(|e| e.code.as_deref()) == Some("1013")
+    ));
+}
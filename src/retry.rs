@@ -0,0 +1,123 @@
+use std::time::{Duration, SystemTime};
+
+use crate::poll::Backoff;
+use http::{header, HeaderMap, StatusCode};
+use smart_default::SmartDefault;
+
+#[derive(Debug, Clone, SmartDefault)]
+pub struct RetryPolicy {
+    #[default(3)]
+    pub max_retries: u32,
+    #[default(Duration::from_millis(200))]
+    pub initial_backoff: Duration,
+    #[default(Duration::from_secs(10))]
+    pub max_backoff: Duration,
+    #[default(2.0)]
+    pub multiplier: f64,
+    #[default(0.2)]
+    pub jitter: f64,
+    #[default(Duration::from_secs(60))]
+    pub max_retry_after: Duration,
+    pub retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    pub fn retry_non_idempotent(mut self, retry_non_idempotent: bool) -> Self {
+        self.retry_non_idempotent = retry_non_idempotent;
+        self
+    }
+
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    pub fn is_retryable_error(err: &reqwest::Error) -> bool {
+        err.is_connect() || err.is_timeout()
+    }
+
+    pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+        let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+        match value.parse::<u64>() {
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => httpdate::parse_http_date(value)
+                .ok()?
+                .duration_since(SystemTime::now())
+                .ok(),
+        }
+    }
+
+    pub(crate) fn backoff(&self) -> Backoff {
+        Backoff::new(
+            self.initial_backoff,
+            self.max_backoff,
+            self.multiplier,
+            self.jitter,
+        )
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_retry_policy_classify() {
+    use http::HeaderValue;
+
+    assert!(RetryPolicy::is_retryable_status(
+        StatusCode::TOO_MANY_REQUESTS
+    ));
+    assert!(RetryPolicy::is_retryable_status(StatusCode::BAD_GATEWAY));
+    assert!(!RetryPolicy::is_retryable_status(StatusCode::BAD_REQUEST));
+    assert!(!RetryPolicy::is_retryable_status(StatusCode::OK));
+
+    let mut headers = HeaderMap::new();
+    assert_eq!(RetryPolicy::retry_after(&headers), None);
+    headers.insert(header::RETRY_AFTER, HeaderValue::from_static("7"));
+    assert_eq!(
+        RetryPolicy::retry_after(&headers),
+        Some(Duration::from_secs(7))
+    );
+    let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+    headers.insert(header::RETRY_AFTER, HeaderValue::from_str(&date).unwrap());
+    let after = RetryPolicy::retry_after(&headers).unwrap();
+    assert!(after > Duration::from_secs(100) && after <= Duration::from_secs(120));
+}
@@ -0,0 +1,686 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asr::{
+        record::{RecordAsrResponse, RecordAsrResult},
+        subtitle::{SubtitleResponse, SubtitleResult},
+    },
+    client::Client,
+    error::*,
+    poll::{JobStatus, PollPolicy},
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use serde_json::Value;
+use tracing::{info, trace, warn};
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Record,
+    Subtitle,
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Record => "record",
+            JobKind::Subtitle => "subtitle",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "record" => Ok(JobKind::Record),
+            "subtitle" => Ok(JobKind::Subtitle),
+            _ => Err(Error::Store(format!("unknown job kind: {}", value))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Submitted,
+    Queued,
+    Processing,
+    Succeeded,
+    Failed,
+}
+
+impl JobState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Submitted => "submitted",
+            JobState::Queued => "queued",
+            JobState::Processing => "processing",
+            JobState::Succeeded => "succeeded",
+            JobState::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "submitted" => Ok(JobState::Submitted),
+            "queued" => Ok(JobState::Queued),
+            "processing" => Ok(JobState::Processing),
+            "succeeded" => Ok(JobState::Succeeded),
+            "failed" => Ok(JobState::Failed),
+            _ => Err(Error::Store(format!("unknown job state: {}", value))),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Succeeded | JobState::Failed)
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+pub struct StoredJob {
+    pub id: String,
+    pub kind: JobKind,
+    pub appid: String,
+    pub cluster: Option<String>,
+    pub state: JobState,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub submitted_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoredJob {
+    fn new(id: String, kind: JobKind, appid: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            kind,
+            appid,
+            cluster: None,
+            state: JobState::Submitted,
+            result: None,
+            error: None,
+            submitted_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn record(rep: &RecordAsrResponse) -> Self {
+        Self {
+            cluster: Some(rep.cluster.clone()),
+            ..Self::new(rep.id.clone(), JobKind::Record, rep.appid.clone())
+        }
+    }
+
+    pub fn subtitle(rep: &SubtitleResponse, appid: impl Into<String>) -> Self {
+        Self::new(rep.id.clone(), JobKind::Subtitle, appid.into())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.is_finished()
+    }
+
+    pub fn record_result(&self) -> Result<Option<RecordAsrResult>> {
+        self.result_as()
+    }
+
+    pub fn subtitle_result(&self) -> Result<Option<SubtitleResult>> {
+        self.result_as()
+    }
+
+    fn result_as<T: serde::de::DeserializeOwned>(&self) -> Result<Option<T>> {
+        match &self.result {
+            Some(result) => Ok(Some(serde_json::from_value(result.clone())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn update(&mut self, state: JobState) {
+        self.state = state;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn succeed(&mut self, result: Value) {
+        self.result = Some(result);
+        self.error = None;
+        self.update(JobState::Succeeded);
+    }
+
+    pub fn fail(&mut self, error: impl ToString) {
+        self.error = Some(error.to_string());
+        self.update(JobState::Failed);
+    }
+
+    pub async fn query(&self, client: &Client, token: &str) -> Result<JobStatus<Value>> {
+        let status = match self.kind {
+            JobKind::Record => {
+                let rep = RecordAsrResponse {
+                    code: 0,
+                    message: String::new(),
+                    id: self.id.clone(),
+                    appid: self.appid.clone(),
+                    token: token.to_string(),
+                    cluster: self.cluster.clone().unwrap_or_default(),
+                };
+                match rep.query(client).await? {
+                    JobStatus::Done(result) => JobStatus::Done(serde_json::to_value(result)?),
+                    JobStatus::Queued => JobStatus::Queued,
+                    JobStatus::Processing => JobStatus::Processing,
+                }
+            }
+            JobKind::Subtitle => {
+                let rep = SubtitleResponse {
+                    code: 0,
+                    message: String::new(),
+                    id: self.id.clone(),
+                };
+                match rep.query(&self.appid, client).await? {
+                    JobStatus::Done(result) => JobStatus::Done(serde_json::to_value(result)?),
+                    JobStatus::Queued => JobStatus::Queued,
+                    JobStatus::Processing => JobStatus::Processing,
+                }
+            }
+        };
+        Ok(status)
+    }
+}
+
+pub trait JobStore: Send + Sync {
+    fn save(&self, job: &StoredJob) -> Result<()>;
+    fn get(&self, id: &str) -> Result<Option<StoredJob>>;
+    fn list(&self) -> Result<Vec<StoredJob>>;
+    fn remove(&self, id: &str) -> Result<()>;
+
+    fn unfinished(&self) -> Result<Vec<StoredJob>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|job| !job.is_finished())
+            .collect())
+    }
+}
+
+impl<T: JobStore + ?Sized> JobStore for Arc<T> {
+    fn save(&self, job: &StoredJob) -> Result<()> {
+        (**self).save(job)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<StoredJob>> {
+        (**self).get(id)
+    }
+
+    fn list(&self) -> Result<Vec<StoredJob>> {
+        (**self).list()
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        (**self).remove(id)
+    }
+
+    fn unfinished(&self) -> Result<Vec<StoredJob>> {
+        (**self).unfinished()
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum LogEntry {
+    Removed { removed: String },
+    Saved(Box<StoredJob>),
+}
+
+#[derive(Debug)]
+struct JobLog {
+    jobs: BTreeMap<String, StoredJob>,
+    entries: usize,
+}
+
+#[derive(Debug)]
+pub struct JsonFileStore {
+    path: PathBuf,
+    log: Mutex<JobLog>,
+}
+
+impl JsonFileStore {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut jobs = BTreeMap::new();
+        let mut entries = 0;
+        if path.exists() {
+            for (no, line) in std::fs::read_to_string(&path)?.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry = serde_json::from_str(line)
+                    .map_err(|e| Error::Store(format!("line {}: {}", no + 1, e)))?;
+                match entry {
+                    LogEntry::Removed { removed } => jobs.remove(&removed),
+                    LogEntry::Saved(job) => jobs.insert(job.id.clone(), *job),
+                };
+                entries += 1;
+            }
+        }
+        let store = Self {
+            path,
+            log: Mutex::new(JobLog { jobs, entries }),
+        };
+        {
+            let mut log = store.log.lock().expect("job store lock");
+            if log.entries > log.jobs.len() {
+                store.compact(&mut log)?;
+            }
+        }
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn compact(&self, log: &mut JobLog) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut content = String::new();
+        for job in log.jobs.values() {
+            content.push_str(&serde_json::to_string(job)?);
+            content.push('\n');
+        }
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(tmp, &self.path)?;
+        log.entries = log.jobs.len();
+        Ok(())
+    }
+
+    fn append(&self, log: &mut JobLog, entry: &LogEntry) -> Result<()> {
+        if log.entries >= 2 * log.jobs.len() + 64 {
+            return self.compact(log);
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        log.entries += 1;
+        Ok(())
+    }
+}
+
+impl JobStore for JsonFileStore {
+    fn save(&self, job: &StoredJob) -> Result<()> {
+        let mut log = self.log.lock().expect("job store lock");
+        log.jobs.insert(job.id.clone(), job.clone());
+        self.append(&mut log, &LogEntry::Saved(Box::new(job.clone())))
+    }
+
+    fn get(&self, id: &str) -> Result<Option<StoredJob>> {
+        Ok(self
+            .log
+            .lock()
+            .expect("job store lock")
+            .jobs
+            .get(id)
+            .cloned())
+    }
+
+    fn list(&self) -> Result<Vec<StoredJob>> {
+        let log = self.log.lock().expect("job store lock");
+        let mut jobs = log.jobs.values().cloned().collect::<Vec<_>>();
+        jobs.sort_by_key(|job| job.submitted_at);
+        Ok(jobs)
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        let mut log = self.log.lock().expect("job store lock");
+        if log.jobs.remove(id).is_some() {
+            self.append(
+                &mut log,
+                &LogEntry::Removed {
+                    removed: id.to_string(),
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub struct SqliteStore {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(rusqlite::Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self> {
+        Self::init(rusqlite::Connection::open_in_memory()?)
+    }
+
+    fn init(conn: rusqlite::Connection) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                appid TEXT NOT NULL,
+                cluster TEXT,
+                state TEXT NOT NULL,
+                result TEXT,
+                error TEXT,
+                submitted_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS jobs_state ON jobs (state);",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn row(row: &rusqlite::Row) -> rusqlite::Result<[Option<String>; 9]> {
+        Ok([
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+            row.get(5)?,
+            row.get(6)?,
+            row.get(7)?,
+            row.get(8)?,
+        ])
+    }
+
+    fn job(row: [Option<String>; 9]) -> Result<StoredJob> {
+        let [id, kind, appid, cluster, state, result, error, submitted_at, updated_at] = row;
+        let time = |v: Option<String>| -> Result<DateTime<Utc>> {
+            DateTime::parse_from_rfc3339(&v.unwrap_or_default())
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| Error::Store(e.to_string()))
+        };
+        Ok(StoredJob {
+            id: id.unwrap_or_default(),
+            kind: JobKind::parse(&kind.unwrap_or_default())?,
+            appid: appid.unwrap_or_default(),
+            cluster,
+            state: JobState::parse(&state.unwrap_or_default())?,
+            result: result.map(|r| serde_json::from_str(&r)).transpose()?,
+            error,
+            submitted_at: time(submitted_at)?,
+            updated_at: time(updated_at)?,
+        })
+    }
+
+    fn query(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<StoredJob>> {
+        let conn = self.conn.lock().expect("job store lock");
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt
+            .query_map(params, Self::row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter().map(Self::job).collect()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+const SELECT_JOBS: &str = "SELECT id, kind, appid, cluster, state, result, error, \
+                           submitted_at, updated_at FROM jobs";
+
+#[cfg(feature = "sqlite")]
+impl JobStore for SqliteStore {
+    fn save(&self, job: &StoredJob) -> Result<()> {
+        let result = job.result.as_ref().map(|r| r.to_string());
+        self.conn.lock().expect("job store lock").execute(
+            "INSERT OR REPLACE INTO jobs
+                (id, kind, appid, cluster, state, result, error, submitted_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                job.id,
+                job.kind.as_str(),
+                job.appid,
+                job.cluster,
+                job.state.as_str(),
+                result,
+                job.error,
+                job.submitted_at.to_rfc3339(),
+                job.updated_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<StoredJob>> {
+        Ok(self
+            .query(&format!("{} WHERE id = ?1", SELECT_JOBS), [id])?
+            .pop())
+    }
+
+    fn list(&self) -> Result<Vec<StoredJob>> {
+        self.query(&format!("{} ORDER BY submitted_at", SELECT_JOBS), [])
+    }
+
+    fn remove(&self, id: &str) -> Result<()> {
+        self.conn
+            .lock()
+            .expect("job store lock")
+            .execute("DELETE FROM jobs WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    fn unfinished(&self) -> Result<Vec<StoredJob>> {
+        self.query(
+            &format!(
+                "{} WHERE state NOT IN ('succeeded', 'failed') ORDER BY submitted_at",
+                SELECT_JOBS
+            ),
+            [],
+        )
+    }
+}
+
+pub(crate) fn is_job_failure(e: &Error) -> bool {
+    e.api().is_some() && !e.is_retryable() && !e.is_auth_error()
+}
+
+pub async fn track(
+    store: &dyn JobStore,
+    mut job: StoredJob,
+    client: &Client,
+    token: &str,
+    policy: impl Into<PollPolicy>,
+) -> Result<StoredJob> {
+    let mut poller = policy.into().poller(&job.id);
+    while !job.is_finished() {
+        poller.next_attempt().await?;
+        let state = job.state;
+        match poller.attempt(job.query(client, token)).await {
+            Ok(JobStatus::Done(result)) => job.succeed(result),
+            Ok(JobStatus::Queued) => job.update(JobState::Queued),
+            Ok(JobStatus::Processing) => job.update(JobState::Processing),
+            Err(e) if is_job_failure(&e) => job.fail(e),
+            Err(e) if e.is_retryable() => {
+                warn!("stored job {} query failed: {}, retrying", job.id, e);
+            }
+            Err(e) => return Err(e),
+        }
+        trace!("stored job {} is {:?}", job.id, job.state);
+        if job.state != state {
+            store.save(&job)?;
+        }
+    }
+    Ok(job)
+}
+
+pub async fn resume(
+    store: &dyn JobStore,
+    client: &Client,
+    token: impl Fn(&StoredJob) -> Option<String>,
+    policy: impl Into<PollPolicy>,
+    concurrency: usize,
+) -> Result<Vec<Result<StoredJob>>> {
+    let policy = policy.into();
+    let jobs = store.unfinished()?;
+    info!(
+        "resuming {} unfinished jobs, concurrency={}",
+        jobs.len(),
+        concurrency
+    );
+    let results = stream::iter(jobs)
+        .map(|job| {
+            let token = match job.kind {
+                JobKind::Record => token(&job),
+                JobKind::Subtitle => Some(token(&job).unwrap_or_default()),
+            };
+            let policy = policy.clone();
+            async move {
+                match token {
+                    Some(token) => track(store, job, client, &token, policy).await,
+                    None => {
+                        warn!("no token for stored job {} of appid {}", job.id, job.appid);
+                        Err(Error::MissingCredential("access_token"))
+                    }
+                }
+            }
+        })
+        .buffered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    for e in results.iter().filter_map(|r| r.as_ref().err()) {
+        warn!("resumed job not finished: {}", e);
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_job_store_resume() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("volcengine-store-{}", std::process::id()));
+    let path = dir.join("jobs.json");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let rep = RecordAsrResponse {
+        code: 1000,
+        message: "Success".into(),
+        id: "record-job".into(),
+        appid: "100".into(),
+        token: "token".into(),
+        cluster: "cluster".into(),
+    };
+    let sub = SubtitleResponse {
+        code: 0,
+        message: "Success".into(),
+        id: "subtitle-job".into(),
+    };
+    let mut done = StoredJob::subtitle(&sub, "100");
+    done.id = "done-job".into();
+    done.fail("boom");
+    let mut orphan = StoredJob::record(&rep);
+    orphan.id = "orphan-job".into();
+    orphan.appid = "300".into();
+
+    {
+        let store = JsonFileStore::open(&path)?;
+        store.save(&StoredJob::record(&rep))?;
+        store.save(&StoredJob::subtitle(&sub, "100"))?;
+        store.save(&done)?;
+        store.save(&orphan)?;
+    }
+
+    let lines = || std::fs::read_to_string(&path).map(|c| c.lines().count());
+    let store = JsonFileStore::open(&path)?;
+    assert_eq!(store.list()?.len(), 4);
+    assert_eq!(store.unfinished()?.len(), 3);
+    assert_eq!(lines()?, 4);
+    assert!(!std::fs::read_to_string(&path)?.contains("token"));
+
+    let paths = Arc::new(Mutex::new(vec![]));
+    let client = Client::builder()
+        .transport(crate::transport::json_mock({
+            let paths = paths.clone();
+            move |req| {
+                let body = crate::transport::request_json(req);
+                let mut paths = paths.lock().unwrap();
+                paths.push(req.url().path().to_string());
+                let polls = paths.iter().filter(|p| *p == req.url().path()).count();
+                match req.url().path() {
+                    "/api/v1/auc/query" => {
+                        assert_eq!(body["token"], "resumed-token");
+                        match polls {
+                            1 => serde_json::json!({"resp": {"id": "record-job", "code": 1005, "message": "busy"}}),
+                            _ => serde_json::json!({"resp": {
+                                "id": "record-job", "code": 1000, "message": "Success",
+                                "text": "hello", "additions": {}, "utterances": []}}),
+                        }
+                    }
+                    _ => match polls {
+                        1..=3 => serde_json::json!({"code": 2000, "message": "running", "id": "subtitle-job"}),
+                        _ => serde_json::json!({"code": 1022, "message": "recognition error", "id": "subtitle-job"}),
+                    },
+                }
+            }
+        }))
+        .build()?;
+
+    let results = resume(
+        &store,
+        &client,
+        |job| (job.appid == "100").then(|| "resumed-token".to_string()),
+        PollPolicy::fixed(std::time::Duration::ZERO),
+        1,
+    )
+    .await?;
+    assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+    assert!(results
+        .iter()
+        .any(|r| matches!(r, Err(Error::MissingCredential(_)))));
+    assert_eq!(
+        store
+            .unfinished()?
+            .into_iter()
+            .map(|job| job.id)
+            .collect::<Vec<_>>(),
+        vec!["orphan-job"]
+    );
+    let paths = paths.lock().unwrap().clone();
+    assert_eq!(paths.len(), 6);
+    assert!(paths.windows(2).filter(|w| w[0] != w[1]).count() <= 1);
+    assert_eq!(lines()?, 7);
+
+    let store = JsonFileStore::open(&path)?;
+    let record = store.get("record-job")?.unwrap();
+    assert_eq!(record.state, JobState::Succeeded);
+    assert_eq!(record.record_result()?.unwrap().id, "record-job");
+    let subtitle = store.get("subtitle-job")?.unwrap();
+    assert_eq!(subtitle.state, JobState::Failed);
+    assert!(subtitle.error.unwrap().contains("recognition error"));
+    assert_eq!(lines()?, 4);
+
+    #[cfg(feature = "sqlite")]
+    {
+        let sqlite = SqliteStore::in_memory()?;
+        for job in store.list()? {
+            sqlite.save(&job)?;
+        }
+        sqlite.save(&StoredJob::record(&rep))?;
+        assert_eq!(sqlite.list()?.len(), 4);
+        assert_eq!(sqlite.unfinished()?.len(), 2);
+        assert_eq!(sqlite.get("subtitle-job")?, store.get("subtitle-job")?);
+        sqlite.remove("record-job")?;
+        assert_eq!(sqlite.get("record-job")?, None);
+    }
+
+    store.remove("done-job")?;
+    assert_eq!(JsonFileStore::open(&path)?.list()?.len(), 3);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,469 @@
+pub mod presign;
+pub mod signer;
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{
+    auth::{signer::uri_encode, CredentialsProvider},
+    client::{Client, ClientBuilder, RawResponse},
+    error::*,
+};
+use chrono::Utc;
+use http::{header, HeaderMap, HeaderValue, Method};
+use rand::Rng;
+use reqwest::{Body, Response};
+use tokio::io::AsyncReadExt;
+use tracing::{debug, info, warn};
+use url::{Host, Url};
+
+pub use presign::Presigner;
+pub use signer::TosSigner;
+
+pub const DEFAULT_PART_SIZE: u64 = 20 * 1024 * 1024;
+pub const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+pub const MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+pub const MAX_PARTS: u64 = 10_000;
+
+pub fn endpoint(region: &str) -> String {
+    format!("https://tos-{}.volces.com", region)
+}
+
+pub fn object_url(endpoint: &Url, bucket: &str, key: &str) -> Result<Url> {
+    let mut url = endpoint.clone();
+    let path = key.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+    let virtual_host = match url.host() {
+        Some(Host::Domain(host)) if host.contains('.') => Some(format!("{}.{}", bucket, host)),
+        _ => None,
+    };
+    match virtual_host {
+        Some(host) => {
+            url.set_host(Some(&host))?;
+            url.set_path(&path);
+        }
+        None => url.set_path(&format!("{}/{}", uri_encode(bucket), path)),
+    }
+    Ok(url)
+}
+
+#[derive(Clone)]
+pub struct TosClient {
+    pub client: Client,
+    pub signer: TosSigner,
+    pub bucket: String,
+    pub endpoint: Url,
+    pub part_size: u64,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct MultipartUpload {
+    #[serde(default)]
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct UploadedPart {
+    pub part_number: u32,
+    #[serde(rename = "ETag")]
+    pub etag: String,
+}
+
+impl TosClient {
+    pub fn new(
+        credentials: impl CredentialsProvider + 'static,
+        region: impl Into<String>,
+        bucket: impl Into<String>,
+    ) -> Result<Self> {
+        Self::with_client(Client::builder(), credentials, region, bucket)
+    }
+
+    pub fn with_client(
+        builder: ClientBuilder,
+        credentials: impl CredentialsProvider + 'static,
+        region: impl Into<String>,
+        bucket: impl Into<String>,
+    ) -> Result<Self> {
+        let signer = TosSigner::new(credentials, region);
+        Ok(Self {
+            client: builder.auth(signer.clone()).build()?,
+            endpoint: Url::parse(&endpoint(&signer.region))?,
+            signer,
+            bucket: bucket.into(),
+            part_size: DEFAULT_PART_SIZE,
+        })
+    }
+
+    pub fn endpoint(mut self, endpoint: impl AsRef<str>) -> Result<Self> {
+        self.endpoint = Url::parse(endpoint.as_ref())?;
+        Ok(self)
+    }
+
+    pub fn part_size(mut self, part_size: u64) -> Self {
+        self.part_size = part_size.clamp(MIN_PART_SIZE, MAX_PART_SIZE);
+        self
+    }
+
+    fn part_size_for(&self, size: u64) -> u64 {
+        self.part_size
+            .clamp(MIN_PART_SIZE, MAX_PART_SIZE)
+            .max(size.div_ceil(MAX_PARTS))
+    }
+
+    pub fn object_url(&self, key: &str) -> Result<Url> {
+        object_url(&self.endpoint, &self.bucket, key)
+    }
+
+    pub fn presigner(&self) -> Presigner {
+        Presigner {
+            signer: self.signer.clone(),
+            endpoint: self.endpoint.clone(),
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        queries: Vec<(String, String)>,
+        headers: Vec<(header::HeaderName, HeaderValue)>,
+        body: Option<Body>,
+    ) -> Result<Response> {
+        let url = self.object_url(key)?;
+        let rep = self
+            .client
+            .call(method, url.as_str(), queries, headers, body)
+            .await?;
+        match rep.status().is_success() {
+            true => Ok(rep),
+            false => Err(RawResponse::read(rep).await?.api_error()),
+        }
+    }
+
+    pub async fn put_object(
+        &self,
+        key: &str,
+        data: impl Into<Vec<u8>>,
+        content_type: Option<&str>,
+    ) -> Result<String> {
+        let mut headers = vec![];
+        if let Some(content_type) = content_type {
+            headers.push((header::CONTENT_TYPE, HeaderValue::from_str(content_type)?));
+        }
+        let rep = self
+            .send(Method::PUT, key, vec![], headers, Some(data.into().into()))
+            .await?;
+        Ok(etag(rep.headers()))
+    }
+
+    pub async fn get_object(&self, key: &str) -> Result<Vec<u8>> {
+        let rep = self.send(Method::GET, key, vec![], vec![], None).await?;
+        Ok(rep.bytes().await?.to_vec())
+    }
+
+    pub async fn delete_object(&self, key: &str) -> Result<()> {
+        self.send(Method::DELETE, key, vec![], vec![], None).await?;
+        Ok(())
+    }
+
+    pub async fn create_multipart_upload(
+        &self,
+        key: &str,
+        content_type: Option<&str>,
+    ) -> Result<MultipartUpload> {
+        let mut headers = vec![];
+        if let Some(content_type) = content_type {
+            headers.push((header::CONTENT_TYPE, HeaderValue::from_str(content_type)?));
+        }
+        let rep = self
+            .send(
+                Method::POST,
+                key,
+                vec![("uploads".into(), "".into())],
+                headers,
+                None,
+            )
+            .await?;
+        RawResponse::read(rep).await?.json()
+    }
+
+    pub async fn upload_part(
+        &self,
+        upload: &MultipartUpload,
+        part_number: u32,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<UploadedPart> {
+        let rep = self
+            .send(
+                Method::PUT,
+                &upload.key,
+                vec![
+                    ("partNumber".into(), part_number.to_string()),
+                    ("uploadId".into(), upload.upload_id.clone()),
+                ],
+                vec![],
+                Some(data.into().into()),
+            )
+            .await?;
+        Ok(UploadedPart {
+            part_number,
+            etag: etag(rep.headers()),
+        })
+    }
+
+    pub async fn complete_multipart_upload(
+        &self,
+        upload: &MultipartUpload,
+        parts: &[UploadedPart],
+    ) -> Result<String> {
+        let body = serde_json::json!({ "Parts": parts });
+        let rep = self
+            .send(
+                Method::POST,
+                &upload.key,
+                vec![("uploadId".into(), upload.upload_id.clone())],
+                vec![(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_str("application/json")?,
+                )],
+                Some(Body::from(serde_json::to_string(&body)?)),
+            )
+            .await?;
+        Ok(etag(rep.headers()))
+    }
+
+    pub async fn abort_multipart_upload(&self, upload: &MultipartUpload) -> Result<()> {
+        self.send(
+            Method::DELETE,
+            &upload.key,
+            vec![("uploadId".into(), upload.upload_id.clone())],
+            vec![],
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn upload_file(&self, key: &str, path: impl AsRef<Path>) -> Result<String> {
+        let path = path.as_ref();
+        let content_type = Some(content_type(path));
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let part_size = self.part_size_for(size);
+
+        if size <= part_size {
+            let mut data = Vec::with_capacity(size as usize);
+            file.read_to_end(&mut data).await?;
+            info!("uploading {:?} to tos://{}/{}", path, self.bucket, key);
+            return self.put_object(key, data, content_type).await;
+        }
+
+        let upload = self.create_multipart_upload(key, content_type).await?;
+        info!(
+            "uploading {:?} to tos://{}/{} in {} parts, upload_id={}",
+            path,
+            self.bucket,
+            key,
+            size.div_ceil(part_size),
+            upload.upload_id
+        );
+
+        let mut parts = vec![];
+        let mut remaining = size;
+        while remaining > 0 {
+            let mut data = Vec::with_capacity(part_size.min(remaining) as usize);
+            (&mut file).take(part_size).read_to_end(&mut data).await?;
+            if data.is_empty() {
+                break;
+            }
+            remaining = remaining.saturating_sub(data.len() as u64);
+            let part_number = parts.len() as u32 + 1;
+            match self.upload_part(&upload, part_number, data).await {
+                Ok(part) => {
+                    debug!("uploaded part {} of {}", part_number, upload.upload_id);
+                    parts.push(part);
+                }
+                Err(e) => {
+                    if let Err(abort) = self.abort_multipart_upload(&upload).await {
+                        warn!("failed to abort upload {}: {}", upload.upload_id, abort);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        self.complete_multipart_upload(&upload, &parts).await
+    }
+
+    pub fn presign_get(&self, key: &str, expires: Duration) -> Result<Url> {
+        self.presigner().get(&self.bucket, key, expires)
+    }
+
+    pub async fn upload_audio(&self, path: impl AsRef<Path>, expires: Duration) -> Result<Url> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("audio");
+        let key = format!(
+            "asr/{}-{:08x}/{}",
+            Utc::now().format("%Y%m%d%H%M%S"),
+            rand::thread_rng().gen::<u32>(),
+            name
+        );
+        self.upload_file(&key, path).await?;
+        self.presign_get(&key, expires)
+    }
+}
+
+pub fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "mp3" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "aac" => "audio/aac",
+        "wav" => "audio/wav",
+        "ogg" | "opus" => "audio/ogg",
+        "flac" => "audio/flac",
+        "amr" => "audio/amr",
+        "webm" => "audio/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+fn etag(headers: &HeaderMap) -> String {
+    headers
+        .get(header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+#[test]
+fn test_object_url() -> Result<()> {
+    let url = object_url(
+        &Url::parse(&endpoint("cn-beijing"))?,
+        "audio",
+        "asr/a b.mp3",
+    )?;
+    assert_eq!(
+        url.as_str(),
+        "https://audio.tos-cn-beijing.volces.com/asr/a%20b.mp3"
+    );
+    let url = object_url(&Url::parse("http://127.0.0.1:9000")?, "audio", "asr/a.mp3")?;
+    assert_eq!(url.as_str(), "http://127.0.0.1:9000/audio/asr/a.mp3");
+    let url = object_url(&Url::parse("http://localhost:9000/")?, "audio", "a.mp3")?;
+    assert_eq!(url.as_str(), "http://localhost:9000/audio/a.mp3");
+    let url = object_url(&Url::parse("http://[::1]:9000")?, "audio", "a.mp3")?;
+    assert_eq!(url.as_str(), "http://[::1]:9000/audio/a.mp3");
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_tos_multipart_upload() -> Result<()> {
+    use crate::auth::Credentials;
+    use reqwest::Request;
+    use std::sync::{Arc, Mutex};
+
+    assert_eq!(content_type(Path::new("a.MP3")), "audio/mpeg");
+    assert_eq!(content_type(Path::new("a.m4a")), "audio/mp4");
+    assert_eq!(content_type(Path::new("a.wav")), "audio/wav");
+    assert_eq!(content_type(Path::new("a.pcm")), "application/octet-stream");
+    assert_eq!(content_type(Path::new("audio")), "application/octet-stream");
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let builder = Client::builder().transport(crate::transport::from_fn({
+        let seen = seen.clone();
+        move |req: Request| {
+            assert!(req.headers()[header::AUTHORIZATION]
+                .to_str()
+                .unwrap()
+                .starts_with("TOS4-HMAC-SHA256 Credential=AK/"));
+            let query = req.url().query().unwrap_or_default().to_string();
+            seen.lock()
+                .unwrap()
+                .push(format!("{} {}", req.method(), query));
+            let rep = http::Response::builder().header(header::ETAG, "\"etag\"");
+            let rep = match (req.method().as_str(), query.as_str()) {
+                ("POST", "uploads=") => {
+                    assert_eq!(
+                        req.url().host_str(),
+                        Some("audio.tos-cn-beijing.volces.com")
+                    );
+                    assert_eq!(req.url().path(), "/asr/a%20b.mp3");
+                    assert_eq!(req.headers()[header::CONTENT_TYPE], "audio/mpeg");
+                    rep.body(r#"{"Bucket": "audio", "Key": "asr/a b.mp3", "UploadId": "up"}"#)
+                }
+                ("PUT", "partNumber=3&uploadId=up") => rep
+                    .status(500)
+                    .body(r#"{"Code": "InternalError", "Message": "boom", "RequestId": "r"}"#),
+                _ => rep.body(""),
+            };
+            async move { Ok(rep.unwrap().into()) }
+        }
+    }));
+    let tos = TosClient::with_client(
+        builder.retry(crate::retry::RetryPolicy::none()),
+        Credentials::access_key("AK", "SK"),
+        "cn-beijing",
+        "audio",
+    )?
+    .part_size(4);
+    assert_eq!(tos.part_size, MIN_PART_SIZE);
+    assert_eq!(tos.clone().part_size(u64::MAX).part_size, MAX_PART_SIZE);
+    assert_eq!(tos.part_size_for(MIN_PART_SIZE * MAX_PARTS), MIN_PART_SIZE);
+    assert_eq!(
+        tos.part_size_for(MIN_PART_SIZE * MAX_PARTS + 1),
+        MIN_PART_SIZE + 1
+    );
+
+    let path = std::env::temp_dir().join(format!("volcengine-tos-{}.mp3", std::process::id()));
+    let part = vec![b'0'; MIN_PART_SIZE as usize];
+    std::fs::write(&path, [&part[..], &part[..], b"0"].concat())?;
+
+    let e = tos.upload_file("asr/a b.mp3", &path).await.unwrap_err();
+    assert_eq!(e.api().unwrap().code.as_deref(), Some("InternalError"));
+    assert_eq!(e.api().unwrap().log_id.as_deref(), Some("r"));
+    assert_eq!(
+        seen.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        vec![
+            "POST uploads=",
+            "PUT partNumber=1&uploadId=up",
+            "PUT partNumber=2&uploadId=up",
+            "PUT partNumber=3&uploadId=up",
+            "DELETE uploadId=up",
+        ]
+    );
+
+    std::fs::write(&path, [&part[..], b"0"].concat())?;
+    assert_eq!(tos.upload_file("asr/a b.mp3", &path).await?, "\"etag\"");
+    assert_eq!(
+        seen.lock().unwrap().drain(..).collect::<Vec<_>>(),
+        vec![
+            "POST uploads=",
+            "PUT partNumber=1&uploadId=up",
+            "PUT partNumber=2&uploadId=up",
+            "POST uploadId=up",
+        ]
+    );
+
+    std::fs::write(&path, b"0123")?;
+    let url = tos.upload_audio(&path, Duration::from_secs(3600)).await?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(url.host_str(), Some("audio.tos-cn-beijing.volces.com"));
+    assert!(url.path().starts_with("/asr/"));
+    assert!(url
+        .query_pairs()
+        .any(|(k, v)| k == "X-Tos-Expires" && v == "3600"));
+    assert_eq!(seen.lock().unwrap()[0], "PUT ");
+    Ok(())
+}
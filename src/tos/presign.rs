@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use crate::{
+    auth::CredentialsProvider,
+    error::*,
+    tos::{endpoint, object_url, signer::TosSigner},
+};
+use chrono::{DateTime, Utc};
+use http::Method;
+use url::Url;
+
+pub const MAX_PRESIGN_EXPIRY: Duration = Duration::from_secs(7 * 24 * 3600);
+
+#[derive(Clone)]
+pub struct Presigner {
+    pub signer: TosSigner,
+    pub endpoint: Url,
+}
+
+impl Presigner {
+    pub fn new(
+        credentials: impl CredentialsProvider + 'static,
+        region: impl Into<String>,
+    ) -> Result<Self> {
+        let signer = TosSigner::new(credentials, region);
+        Ok(Self {
+            endpoint: Url::parse(&endpoint(&signer.region))?,
+            signer,
+        })
+    }
+
+    pub fn endpoint(mut self, endpoint: impl AsRef<str>) -> Result<Self> {
+        self.endpoint = Url::parse(endpoint.as_ref())?;
+        Ok(self)
+    }
+
+    pub fn get(&self, bucket: &str, key: &str, expires: Duration) -> Result<Url> {
+        self.presign(Method::GET, bucket, key, expires, Utc::now())
+    }
+
+    pub fn put(&self, bucket: &str, key: &str, expires: Duration) -> Result<Url> {
+        self.presign(Method::PUT, bucket, key, expires, Utc::now())
+    }
+
+    pub fn presign(
+        &self,
+        method: Method,
+        bucket: &str,
+        key: &str,
+        expires: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Url> {
+        if expires.as_secs() == 0 || expires > MAX_PRESIGN_EXPIRY {
+            return Err(Error::PresignExpiry(expires));
+        }
+        let url = object_url(&self.endpoint, bucket, key)?;
+        self.signer.presign(&method, &url, expires, now)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_presign_vectors() -> Result<()> {
+    use crate::auth::Credentials;
+    use chrono::TimeZone;
+
+    let now = Utc.with_ymd_and_hms(2024, 8, 28, 12, 30, 45).unwrap();
+    let credentials = Credentials::access_key("AKLTEXAMPLE", "c2VjcmV0LWtleS1leGFtcGxl");
+
+    let presigner = Presigner::new(credentials.clone(), "cn-beijing")?;
+    let url = presigner.presign(
+        Method::GET,
+        "audio",
+        "asr/2024/a b+c.mp3",
+        Duration::from_secs(3600),
+        now,
+    )?;
+    assert_eq!(
+        url.as_str(),
+        "https://audio.tos-cn-beijing.volces.com/asr/2024/a%20b%2Bc.mp3\
+         ?X-Tos-Algorithm=TOS4-HMAC-SHA256\
+         &X-Tos-Credential=AKLTEXAMPLE%2F20240828%2Fcn-beijing%2Ftos%2Frequest\
+         &X-Tos-Date=20240828T123045Z&X-Tos-Expires=3600&X-Tos-SignedHeaders=host\
+         &X-Tos-Signature=9503a503740ccfe3a19b0c80a5ac2429c2ec501f2723a7cbe68b015021ea8453"
+    );
+
+    let presigner = Presigner::new(credentials.with_session_token("token/1="), "cn-shanghai")?
+        .endpoint("https://tos-s3-cn-shanghai.volces.com")?;
+    let url = presigner.presign(
+        Method::PUT,
+        "media",
+        "中文.wav",
+        Duration::from_secs(600),
+        now,
+    )?;
+    assert_eq!(
+        url.as_str(),
+        "https://media.tos-s3-cn-shanghai.volces.com/%E4%B8%AD%E6%96%87.wav\
+         ?X-Tos-Algorithm=TOS4-HMAC-SHA256\
+         &X-Tos-Credential=AKLTEXAMPLE%2F20240828%2Fcn-shanghai%2Ftos%2Frequest\
+         &X-Tos-Date=20240828T123045Z&X-Tos-Expires=600&X-Tos-SignedHeaders=host\
+         &X-Tos-Security-Token=token%2F1%3D\
+         &X-Tos-Signature=c3ed0e14cb4c55d9d4979fc91902655e798e74d3d0309d66026b54c406c6b476"
+    );
+
+    assert!(matches!(
+        presigner.get("media", "a.wav", Duration::ZERO),
+        Err(Error::PresignExpiry(_))
+    ));
+    assert!(matches!(
+        presigner.get(
+            "media",
+            "a.wav",
+            MAX_PRESIGN_EXPIRY + Duration::from_secs(1)
+        ),
+        Err(Error::PresignExpiry(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    auth::{
+        signer::{canonical_request, hmac_sha256, host, signing_key},
+        Authorizer, CredentialsProvider,
+    },
+    error::*,
+};
+use chrono::{DateTime, Utc};
+use http::{header, HeaderName, HeaderValue, Method};
+use reqwest::Request;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+pub const TOS_ALGORITHM: &str = "TOS4-HMAC-SHA256";
+pub const TOS_SERVICE: &str = "tos";
+pub const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+const X_TOS_DATE: HeaderName = HeaderName::from_static("x-tos-date");
+const X_TOS_CONTENT_SHA256: HeaderName = HeaderName::from_static("x-tos-content-sha256");
+const X_TOS_SECURITY_TOKEN: HeaderName = HeaderName::from_static("x-tos-security-token");
+
+#[derive(Clone)]
+pub struct TosSigner {
+    pub credentials: Arc<dyn CredentialsProvider>,
+    pub region: String,
+}
+
+impl TosSigner {
+    pub fn new(credentials: impl CredentialsProvider + 'static, region: impl Into<String>) -> Self {
+        Self {
+            credentials: Arc::new(credentials),
+            region: region.into(),
+        }
+    }
+
+    fn scope(&self, date: &str) -> String {
+        format!("{}/{}/{}/request", date, self.region, TOS_SERVICE)
+    }
+
+    fn signature(&self, secret: &str, x_date: &str, canonical_request: &str) -> String {
+        let date = &x_date[..8];
+        let string_to_sign = [
+            TOS_ALGORITHM,
+            x_date,
+            &self.scope(date),
+            &hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        ]
+        .join("\n");
+        let key = signing_key(secret, date, &self.region, TOS_SERVICE);
+        hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()))
+    }
+
+    pub fn sign(&self, req: &mut Request, now: DateTime<Utc>) -> Result<()> {
+        let credentials = self.credentials.credentials()?;
+        let (access_key_id, secret_access_key) = credentials.require_access_key()?;
+
+        let x_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let payload_hash = match req.body() {
+            Some(body) => match body.as_bytes() {
+                Some(bytes) => hex::encode(Sha256::digest(bytes)),
+                None => UNSIGNED_PAYLOAD.to_string(),
+            },
+            None => hex::encode(Sha256::digest([])),
+        };
+
+        let headers = req.headers_mut();
+        headers.insert(X_TOS_DATE, HeaderValue::from_str(&x_date)?);
+        headers.insert(X_TOS_CONTENT_SHA256, HeaderValue::from_str(&payload_hash)?);
+        if let Some(token) = &credentials.session_token {
+            headers.insert(X_TOS_SECURITY_TOKEN, HeaderValue::from_str(token)?);
+        }
+
+        let mut signed = vec![("host".to_string(), host(req.url()))];
+        for (name, value) in req.headers() {
+            let name = name.as_str();
+            if name == "content-type" || name == "content-md5" || name.starts_with("x-tos-") {
+                signed.push((
+                    name.to_string(),
+                    value.to_str().unwrap_or_default().trim().into(),
+                ));
+            }
+        }
+
+        let (signed_headers, canonical_request) =
+            canonical_request(req.method(), req.url(), signed, &payload_hash);
+
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            TOS_ALGORITHM,
+            access_key_id,
+            self.scope(&x_date[..8]),
+            signed_headers,
+            self.signature(secret_access_key, &x_date, &canonical_request)
+        );
+
+        req.headers_mut().insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_str(&authorization)?,
+        );
+
+        Ok(())
+    }
+
+    pub fn presign(
+        &self,
+        method: &Method,
+        url: &Url,
+        expires: Duration,
+        now: DateTime<Utc>,
+    ) -> Result<Url> {
+        let credentials = self.credentials.credentials()?;
+        let (access_key_id, secret_access_key) = credentials.require_access_key()?;
+
+        let x_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut url = url.clone();
+        url.query_pairs_mut()
+            .append_pair("X-Tos-Algorithm", TOS_ALGORITHM)
+            .append_pair(
+                "X-Tos-Credential",
+                &format!("{}/{}", access_key_id, self.scope(&x_date[..8])),
+            )
+            .append_pair("X-Tos-Date", &x_date)
+            .append_pair("X-Tos-Expires", &expires.as_secs().to_string())
+            .append_pair("X-Tos-SignedHeaders", "host");
+        if let Some(token) = &credentials.session_token {
+            url.query_pairs_mut()
+                .append_pair("X-Tos-Security-Token", token);
+        }
+
+        let signed = vec![("host".to_string(), host(&url))];
+        let (_, canonical_request) = canonical_request(method, &url, signed, UNSIGNED_PAYLOAD);
+
+        let signature = self.signature(secret_access_key, &x_date, &canonical_request);
+        url.query_pairs_mut()
+            .append_pair("X-Tos-Signature", &signature);
+
+        Ok(url)
+    }
+}
+
+impl Authorizer for TosSigner {
+    fn authorize(&self, req: &mut Request) -> Result<()> {
+        self.sign(req, Utc::now())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_tos_signer_vector() -> Result<()> {
+    use crate::auth::Credentials;
+    use chrono::TimeZone;
+
+    let credentials = Credentials::access_key("AKLTEXAMPLE", "c2VjcmV0LWtleS1leGFtcGxl");
+    let signer = TosSigner::new(credentials, "cn-beijing");
+    let now = Utc.with_ymd_and_hms(2024, 8, 28, 12, 30, 45).unwrap();
+
+    let mut req = reqwest::Client::new()
+        .request(
+            Method::PUT,
+            "https://audio.tos-cn-beijing.volces.com/asr/a%20b.mp3",
+        )
+        .header(header::CONTENT_TYPE, "audio/mp3")
+        .body("ID3")
+        .build()?;
+    signer.sign(&mut req, now)?;
+
+    let headers = req.headers();
+    assert_eq!(headers["x-tos-date"], "20240828T123045Z");
+    assert_eq!(
+        headers["x-tos-content-sha256"],
+        "041149186b23a9502aea5a4e81bbc9620d2b4b76b4bad94e36c744abcf9d3771"
+    );
+    assert_eq!(
+        headers[header::AUTHORIZATION],
+        "TOS4-HMAC-SHA256 Credential=AKLTEXAMPLE/20240828/cn-beijing/tos/request, \
+         SignedHeaders=content-type;host;x-tos-content-sha256;x-tos-date, \
+         Signature=9e13f57e06a6f0d1d89af9371c7d6236662ec691e848a142a35e1250386ba997"
+    );
+    Ok(())
+}
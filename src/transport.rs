@@ -0,0 +1,205 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::*;
+use reqwest::{Request, Response};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::debug;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub type Layer = Box<dyn FnOnce(Arc<dyn Transport>) -> Arc<dyn Transport>>;
+
+pub trait Transport: Send + Sync {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>>;
+}
+
+impl Transport for reqwest::Client {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move { Ok(reqwest::Client::execute(self, req).await?) })
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        (**self).execute(req)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        (**self).execute(req)
+    }
+}
+
+pub struct FnTransport<F>(pub F);
+
+pub fn from_fn<F, Fut>(f: F) -> FnTransport<F>
+where
+    F: Fn(Request) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response>> + Send + 'static,
+{
+    FnTransport(f)
+}
+
+impl<F, Fut> Transport for FnTransport<F>
+where
+    F: Fn(Request) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Response>> + Send + 'static,
+{
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin((self.0)(req))
+    }
+}
+
+pub struct Traced<T> {
+    pub inner: T,
+}
+
+impl<T> Traced<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Transport> Transport for Traced<T> {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let method = req.method().clone();
+            let url = req.url().clone();
+            let started = Instant::now();
+            let rep = self.inner.execute(req).await;
+            match &rep {
+                Ok(rep) => debug!(
+                    "{} {} -> {} in {:?}",
+                    method,
+                    url,
+                    rep.status(),
+                    started.elapsed()
+                ),
+                Err(e) => debug!("{} {} -> {} in {:?}", method, url, e, started.elapsed()),
+            }
+            rep
+        })
+    }
+}
+
+pub struct RateLimited<T> {
+    pub inner: T,
+    pub interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl<T> RateLimited<T> {
+    pub fn new(inner: T, interval: Duration) -> Self {
+        Self {
+            inner,
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn per_second(inner: T, requests: u32) -> Self {
+        Self::new(inner, Duration::from_secs(1) / requests.max(1))
+    }
+}
+
+impl<T: Transport> Transport for RateLimited<T> {
+    fn execute(&self, req: Request) -> BoxFuture<'_, Result<Response>> {
+        Box::pin(async move {
+            let at = {
+                let mut next = self.next.lock().await;
+                let at = (*next).max(Instant::now());
+                *next = at + self.interval;
+                at
+            };
+            tokio::time::sleep_until(at).await;
+            self.inner.execute(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(http::HeaderName, String)>,
+    pub body: serde_json::Value,
+    pub delay: Duration,
+}
+
+#[cfg(test)]
+impl MockResponse {
+    pub fn json(body: serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            headers: vec![],
+            body,
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn header<K>(mut self, name: K, value: impl Into<String>) -> Self
+    where
+        K: TryInto<http::HeaderName>,
+        K::Error: std::fmt::Debug,
+    {
+        let name = name.try_into().expect("mock header name");
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[cfg(test)]
+impl From<serde_json::Value> for MockResponse {
+    fn from(value: serde_json::Value) -> Self {
+        Self::json(value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn request_json(req: &Request) -> serde_json::Value {
+    req.body()
+        .and_then(|b| b.as_bytes())
+        .and_then(|b| serde_json::from_slice(b).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) fn json_mock<F, R>(handler: F) -> impl Transport
+where
+    F: Fn(&Request) -> R + Send + Sync + 'static,
+    R: Into<MockResponse>,
+{
+    from_fn(move |req: Request| {
+        let MockResponse {
+            status,
+            headers,
+            body,
+            delay,
+        } = handler(&req).into();
+        let mut rep = http::Response::builder().status(status);
+        for (name, value) in headers {
+            rep = rep.header(name, value);
+        }
+        let rep = rep.body(body.to_string()).expect("mock response");
+        async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok(rep.into())
+        }
+    })
+}